@@ -0,0 +1,15 @@
+use crate::list_item::ListItem;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// An item moved out of the list into its archive, along with everything
+/// below it as it was at the time.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ArchivedItem {
+    pub item: ListItem,
+    pub descendants: Vec<ListItem>,
+    /// Where the item was, so it can be restored there.
+    pub parent_id: Option<String>,
+    pub index: usize,
+    pub archived_at: DateTime<Utc>,
+}
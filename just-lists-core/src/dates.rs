@@ -0,0 +1,43 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+
+/// Parses a date typed by the user, relative to `today`.
+///
+/// Accepts `YYYY-MM-DD`, `today`, `tomorrow`, `yesterday`, a weekday name
+/// (`fri`, `friday`) for its next occurrence, and offsets such as `+3`
+/// (days), `+3d`, `+2w` or `+1m`. Returns `None` if the input is not
+/// recognised.
+pub fn parse_date(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let input = input.trim().to_lowercase();
+
+    if let Ok(date) = NaiveDate::parse_from_str(&input, "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    match input.as_str() {
+        "today" | "tod" => return Some(today),
+        "tomorrow" | "tom" => return today.checked_add_days(Days::new(1)),
+        "yesterday" => return today.checked_sub_days(Days::new(1)),
+        _ => (),
+    }
+
+    if let Ok(weekday) = input.parse::<Weekday>() {
+        let days_ahead =
+            (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday() - 1) % 7
+                + 1;
+        return today.checked_add_days(Days::new(days_ahead.into()));
+    }
+
+    let offset = input.strip_prefix('+')?;
+    let (amount, unit) = match offset.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => offset.split_at(index),
+        None => (offset, "d"),
+    };
+    let amount: u32 = amount.parse().ok()?;
+
+    match unit {
+        "d" => today.checked_add_days(Days::new(amount.into())),
+        "w" => today.checked_add_days(Days::new(u64::from(amount) * 7)),
+        "m" => today.checked_add_months(Months::new(amount)),
+        _ => None,
+    }
+}
@@ -0,0 +1,781 @@
+use crate::archive::ArchivedItem;
+use crate::list::{List, ListItemDeletionError, ListItemTaskError, LoadError};
+use crate::list_item::ListItem;
+use crate::outline::OutlineItem;
+use crate::tags::extract_tags;
+use crate::workflow::Workflow;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A reversible change to a [`List`].
+///
+/// Commands are small building blocks: structural changes are expressed as
+/// links and unlinks at an exact index, and items that become unreachable are
+/// deleted explicitly, so every command has an exact inverse.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Adds the item to the store without linking it anywhere.
+    InsertItem {
+        item: ListItem,
+    },
+    /// Removes the item from the store. It must no longer be linked anywhere.
+    DeleteItem {
+        item: ListItem,
+    },
+    /// Places the item under `parent_id` (or at the top level) at `index`.
+    Link {
+        item_id: String,
+        parent_id: Option<String>,
+        index: usize,
+    },
+    /// Removes the item found at `index` under `parent_id` (or the top level).
+    Unlink {
+        item_id: String,
+        parent_id: Option<String>,
+        index: usize,
+    },
+    /// Replaces everything but the children of an item.
+    Edit {
+        before: ListItem,
+        after: ListItem,
+    },
+    /// Adds an entry to the archive. The items it holds must already have
+    /// been unlinked.
+    Archive {
+        entry: ArchivedItem,
+    },
+    /// Removes an entry from the archive.
+    Unarchive {
+        entry: ArchivedItem,
+    },
+    Batch(Vec<Command>),
+}
+
+impl Command {
+    pub fn apply(&self, list: &mut List) -> Result<(), CommandError> {
+        match self {
+            Command::InsertItem { item } => {
+                if list.list_item_store.contains_key(&item.id) {
+                    return Err(CommandError::ItemAlreadyExists(item.id.clone()));
+                }
+
+                list.list_item_store.insert(item.id.clone(), item.clone());
+            }
+            Command::DeleteItem { item } => {
+                if list.list_item_store.remove(&item.id).is_none() {
+                    return Err(CommandError::ItemDoesNotExist(item.id.clone()));
+                }
+            }
+            Command::Link {
+                item_id,
+                parent_id,
+                index,
+            } => match parent_id {
+                Some(parent_id) => {
+                    let children_count = list.children_count(parent_id)?;
+                    list.add_existing_child_list_item(
+                        item_id,
+                        parent_id,
+                        Some((*index).min(children_count)),
+                    )?;
+                }
+                None => {
+                    if !list.list_item_store.contains_key(item_id) {
+                        return Err(CommandError::ItemDoesNotExist(item_id.clone()));
+                    }
+
+                    if list.top_level_items.contains(item_id) {
+                        return Err(ListItemTaskError::ParentAlreadyHasItem.into());
+                    }
+
+                    let index = (*index).min(list.top_level_items.len());
+                    list.top_level_items.insert(index, item_id.clone());
+                }
+            },
+            Command::Unlink {
+                item_id,
+                parent_id,
+                index,
+            } => {
+                let siblings = match parent_id {
+                    Some(parent_id) => match list.list_item_store.get_mut(parent_id) {
+                        Some(parent) => &mut parent.children,
+                        None => return Err(CommandError::ItemDoesNotExist(parent_id.clone())),
+                    },
+                    None => &mut list.top_level_items,
+                };
+
+                if siblings.get(*index) != Some(item_id) {
+                    return Err(ListItemDeletionError::ChildNotInParent.into());
+                }
+
+                siblings.remove(*index);
+            }
+            Command::Edit { before: _, after } => {
+                let Some(item) = list.list_item_store.get_mut(&after.id) else {
+                    return Err(CommandError::ItemDoesNotExist(after.id.clone()));
+                };
+
+                let children = std::mem::take(&mut item.children);
+                *item = after.clone();
+                item.children = children;
+            }
+            Command::Archive { entry } => list.archive.push(entry.clone()),
+            Command::Unarchive { entry } => {
+                let Some(position) = list.archive.iter().rposition(|e| e == entry) else {
+                    return Err(CommandError::ItemDoesNotExist(entry.item.id.clone()));
+                };
+
+                list.archive.remove(position);
+            }
+            Command::Batch(commands) => {
+                for (index, command) in commands.iter().enumerate() {
+                    if let Err(error) = command.apply(list) {
+                        for applied in commands[..index].iter().rev() {
+                            _ = applied.inverse().apply(list);
+                        }
+
+                        return Err(error);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn inverse(&self) -> Command {
+        match self {
+            Command::InsertItem { item } => Command::DeleteItem { item: item.clone() },
+            Command::DeleteItem { item } => Command::InsertItem { item: item.clone() },
+            Command::Link {
+                item_id,
+                parent_id,
+                index,
+            } => Command::Unlink {
+                item_id: item_id.clone(),
+                parent_id: parent_id.clone(),
+                index: *index,
+            },
+            Command::Unlink {
+                item_id,
+                parent_id,
+                index,
+            } => Command::Link {
+                item_id: item_id.clone(),
+                parent_id: parent_id.clone(),
+                index: *index,
+            },
+            Command::Edit { before, after } => Command::Edit {
+                before: after.clone(),
+                after: before.clone(),
+            },
+            Command::Archive { entry } => Command::Unarchive {
+                entry: entry.clone(),
+            },
+            Command::Unarchive { entry } => Command::Archive {
+                entry: entry.clone(),
+            },
+            Command::Batch(commands) => {
+                Command::Batch(commands.iter().rev().map(|c| c.inverse()).collect())
+            }
+        }
+    }
+
+    /// Adds a new item under `parent_id` (or at the top level), appending it
+    /// when `index` is `None`.
+    pub fn add_item(
+        list: &List,
+        item: ListItem,
+        parent_id: Option<&str>,
+        index: Option<usize>,
+    ) -> Result<Command, CommandError> {
+        let item_id = item.id.clone();
+        let link = Command::link(list, &item_id, parent_id, index)?;
+
+        Ok(Command::Batch(vec![Command::InsertItem { item }, link]))
+    }
+
+    /// Adds an independent copy of the item and everything below it under
+    /// `parent_id` (or at the top level). See [`List::deep_clone_subtree`].
+    pub fn add_copy(
+        list: &List,
+        item_id: &str,
+        parent_id: Option<&str>,
+        index: Option<usize>,
+    ) -> Result<Command, CommandError> {
+        let Some(copies) = list.deep_clone_subtree(item_id) else {
+            return Err(CommandError::ItemDoesNotExist(item_id.to_string()));
+        };
+
+        let link = Command::link(list, &copies[0].id, parent_id, index)?;
+
+        let mut commands: Vec<Command> = copies
+            .into_iter()
+            .map(|item| Command::InsertItem { item })
+            .collect();
+        commands.push(link);
+
+        Ok(Command::Batch(commands))
+    }
+
+    /// Replaces the occurrence of a shared item under `parent_id` (or at the
+    /// top level) with an independent copy of it, leaving the other
+    /// occurrences untouched.
+    pub fn unshare(
+        list: &List,
+        item_id: &str,
+        parent_id: Option<&str>,
+    ) -> Result<Command, CommandError> {
+        if list.reference_count(item_id) < 2 {
+            return Err(CommandError::NotShared(item_id.to_string()));
+        }
+
+        let index = list.get_index_in(item_id, parent_id)?;
+        let Some(copies) = list.deep_clone_subtree(item_id) else {
+            return Err(CommandError::ItemDoesNotExist(item_id.to_string()));
+        };
+
+        let link = Command::Link {
+            item_id: copies[0].id.clone(),
+            parent_id: parent_id.map(|p| p.to_string()),
+            index,
+        };
+
+        // The item is still referenced elsewhere, so unlinking it here never
+        // leaves anything unreachable.
+        let mut commands = vec![Command::Unlink {
+            item_id: item_id.to_string(),
+            parent_id: parent_id.map(|p| p.to_string()),
+            index,
+        }];
+        commands.extend(copies.into_iter().map(|item| Command::InsertItem { item }));
+        commands.push(link);
+
+        Ok(Command::Batch(commands))
+    }
+
+    /// Places an existing item under `parent_id` (or at the top level),
+    /// appending it when `index` is `None`.
+    pub fn link(
+        list: &List,
+        item_id: &str,
+        parent_id: Option<&str>,
+        index: Option<usize>,
+    ) -> Result<Command, CommandError> {
+        let siblings_count = match parent_id {
+            Some(parent_id) => list.children_count(parent_id)?,
+            None => list.top_level_items.len(),
+        };
+
+        Ok(Command::Link {
+            item_id: item_id.to_string(),
+            parent_id: parent_id.map(|p| p.to_string()),
+            index: index.unwrap_or(siblings_count).min(siblings_count),
+        })
+    }
+
+    /// Unlinks a single occurrence of the item, deleting whatever becomes
+    /// unreachable as a result. See [`List::remove_child_list_item`].
+    pub fn unlink(
+        list: &List,
+        item_id: &str,
+        parent_id: Option<&str>,
+    ) -> Result<Command, CommandError> {
+        let index = match parent_id {
+            Some(parent_id) => list.get_index_of_child(item_id, parent_id)?,
+            None => list.get_index_of_top_level_item(item_id)?,
+        };
+
+        let mut after = list.clone();
+        let removed = after.remove_child_list_item(item_id, parent_id)?;
+
+        let mut commands = vec![Command::Unlink {
+            item_id: item_id.to_string(),
+            parent_id: parent_id.map(|p| p.to_string()),
+            index,
+        }];
+        commands.extend(removed.into_iter().map(|item| Command::DeleteItem { item }));
+
+        Ok(Command::Batch(commands))
+    }
+
+    /// Moves a single occurrence of the item. See [`List::move_item`].
+    pub fn move_item(
+        list: &List,
+        item_id: &str,
+        from_parent_id: Option<&str>,
+        to_parent_id: Option<&str>,
+        index: usize,
+    ) -> Result<Command, CommandError> {
+        let mut after = list.clone();
+        after.move_item(item_id, from_parent_id, to_parent_id, index)?;
+
+        Ok(Command::Batch(vec![
+            Command::Unlink {
+                item_id: item_id.to_string(),
+                parent_id: from_parent_id.map(|p| p.to_string()),
+                index: list.get_index_in(item_id, from_parent_id)?,
+            },
+            Command::Link {
+                item_id: item_id.to_string(),
+                parent_id: to_parent_id.map(|p| p.to_string()),
+                index: after.get_index_in(item_id, to_parent_id)?,
+            },
+        ]))
+    }
+
+    /// Unlinks every occurrence of the item and deletes whatever becomes
+    /// unreachable as a result. See [`List::remove_list_item`].
+    pub fn remove_everywhere(list: &List, item_id: &str) -> Result<Command, CommandError> {
+        let mut commands = Vec::new();
+
+        if let Ok(index) = list.get_index_of_top_level_item(item_id) {
+            commands.push(Command::Unlink {
+                item_id: item_id.to_string(),
+                parent_id: None,
+                index,
+            });
+        }
+
+        for parent_id in list.get_parent_ids(item_id) {
+            commands.push(Command::Unlink {
+                item_id: item_id.to_string(),
+                parent_id: Some(parent_id.to_string()),
+                index: list.get_index_of_child(item_id, parent_id)?,
+            });
+        }
+
+        let mut after = list.clone();
+        let removed = after.remove_list_item(item_id)?;
+        commands.extend(removed.into_iter().map(|item| Command::DeleteItem { item }));
+
+        Ok(Command::Batch(commands))
+    }
+
+    /// Moves a single occurrence of the item into the archive, along with
+    /// everything below it. Descendants still referenced from elsewhere stay
+    /// in the list as well.
+    pub fn archive(
+        list: &List,
+        item_id: &str,
+        parent_id: Option<&str>,
+        archived_at: DateTime<Utc>,
+    ) -> Result<Command, CommandError> {
+        let Some(item) = list.get_list_item(item_id) else {
+            return Err(CommandError::ItemDoesNotExist(item_id.to_string()));
+        };
+
+        let index = list.get_index_in(item_id, parent_id)?;
+
+        let entry = ArchivedItem {
+            item: item.clone(),
+            descendants: list.get_descendants(item_id).into_iter().cloned().collect(),
+            parent_id: parent_id.map(|p| p.to_string()),
+            index,
+            archived_at,
+        };
+
+        Ok(Command::Batch(vec![
+            Command::unlink(list, item_id, parent_id)?,
+            Command::Archive { entry },
+        ]))
+    }
+
+    /// Takes an entry out of the archive and puts its item back where it was
+    /// archived from, or at the top level if that parent no longer exists.
+    /// Items that are still in the list are linked rather than restored.
+    pub fn restore(list: &List, entry: &ArchivedItem) -> Result<Command, CommandError> {
+        let mut commands = vec![Command::Unarchive {
+            entry: entry.clone(),
+        }];
+
+        commands.extend(
+            std::iter::once(&entry.item)
+                .chain(entry.descendants.iter())
+                .filter(|item| list.get_list_item(&item.id).is_none())
+                .map(|item| Command::InsertItem { item: item.clone() }),
+        );
+
+        let parent_id = entry
+            .parent_id
+            .as_deref()
+            .filter(|parent_id| list.get_list_item(parent_id).is_some());
+
+        commands.push(Command::link(
+            list,
+            &entry.item.id,
+            parent_id,
+            Some(entry.index),
+        )?);
+
+        Ok(Command::Batch(commands))
+    }
+
+    /// Changes the fields of an item. Changes made to its children are ignored.
+    pub fn edit(
+        list: &List,
+        item_id: &str,
+        change: impl FnOnce(&mut ListItem),
+    ) -> Result<Command, CommandError> {
+        let Some(before) = list.get_list_item(item_id) else {
+            return Err(CommandError::ItemDoesNotExist(item_id.to_string()));
+        };
+
+        let mut after = before.clone();
+        change(&mut after);
+        after.id = before.id.clone();
+        after.children = before.children.clone();
+
+        Ok(Command::Edit {
+            before: before.clone(),
+            after,
+        })
+    }
+}
+
+/// A set of commands applied to a list one after the other. Dropping the
+/// transaction without committing it reverts everything it applied.
+pub struct Transaction<'a> {
+    list: &'a mut List,
+    applied: Vec<Command>,
+}
+
+impl<'a> Transaction<'a> {
+    pub fn new(list: &'a mut List) -> Transaction<'a> {
+        Transaction {
+            list,
+            applied: Vec::new(),
+        }
+    }
+
+    /// The list with every command applied so far.
+    pub fn list(&self) -> &List {
+        self.list
+    }
+
+    pub fn apply(&mut self, command: Command) -> Result<(), CommandError> {
+        command.apply(self.list)?;
+        self.applied.push(command);
+        Ok(())
+    }
+
+    /// Completes every ancestor of `id` whose children are now all done,
+    /// working upwards so a finished project can in turn finish its parent.
+    /// Nothing happens if the workflow has no finished state.
+    pub fn complete_finished_ancestors(
+        &mut self,
+        id: &str,
+        workflow: &Workflow,
+    ) -> Result<(), CommandError> {
+        let Some(completed) = workflow.completed_state() else {
+            return Ok(());
+        };
+
+        let mut pending: Vec<String> = vec![id.to_string()];
+
+        while let Some(current_id) = pending.pop() {
+            let finished_parents: Vec<String> = self
+                .list
+                .get_parent_ids(&current_id)
+                .into_iter()
+                .filter(|parent_id| {
+                    self.list.children_done(parent_id, workflow)
+                        && self
+                            .list
+                            .get_list_item(parent_id)
+                            .is_some_and(|parent| !workflow.is_done(&parent.state))
+                })
+                .map(str::to_string)
+                .collect();
+
+            for parent_id in finished_parents {
+                self.apply(Command::edit(self.list, &parent_id, |parent| {
+                    parent.state = completed.clone();
+                })?)?;
+                pending.push(parent_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Makes the item `root_id` and everything below it match an outline
+    /// that passed [`OutlineItem::check`]. Lines without an id become new
+    /// items, changed values are saved and the children of every item are
+    /// replaced with the ones listed at its first appearance. Items that are
+    /// no longer reachable are deleted. `#tag` words add tags, as when
+    /// editing an item.
+    pub fn apply_outline(
+        &mut self,
+        root_id: &str,
+        outline: &OutlineItem,
+    ) -> Result<(), CommandError> {
+        let candidates = self.list.get_subtree_ids(root_id);
+        let mut children: Vec<(String, Vec<String>)> = Vec::new();
+        self.apply_outline_items(root_id.to_string(), outline, &mut children)?;
+
+        // Unlinking everything that goes first means moving an item below
+        // one of its former descendants does not look like a cycle.
+        let wanted: HashMap<&str, &Vec<String>> = children
+            .iter()
+            .map(|(id, children)| (id.as_str(), children))
+            .collect();
+
+        for (parent_id, _) in &children {
+            let current = self
+                .list
+                .get_list_item(parent_id)
+                .map_or_else(Vec::new, |parent| parent.children.clone());
+
+            for (index, child_id) in current.iter().enumerate().rev() {
+                if !wanted[parent_id.as_str()].contains(child_id) {
+                    self.apply(Command::Unlink {
+                        item_id: child_id.clone(),
+                        parent_id: Some(parent_id.clone()),
+                        index,
+                    })?;
+                }
+            }
+        }
+
+        for (parent_id, wanted_children) in &children {
+            for (index, child_id) in wanted_children.iter().enumerate() {
+                let current = self.list.get_index_in(child_id, Some(parent_id)).ok();
+
+                if current == Some(index) {
+                    continue;
+                }
+
+                if let Some(current) = current {
+                    self.apply(Command::Unlink {
+                        item_id: child_id.clone(),
+                        parent_id: Some(parent_id.clone()),
+                        index: current,
+                    })?;
+                }
+
+                self.apply(Command::Link {
+                    item_id: child_id.clone(),
+                    parent_id: Some(parent_id.clone()),
+                    index,
+                })?;
+            }
+        }
+
+        let mut after = self.list.clone();
+
+        for item in after.collect_garbage(candidates) {
+            self.apply(Command::DeleteItem { item })?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds and edits the items of the outline, collecting the children
+    /// wanted for each item at its first appearance.
+    fn apply_outline_items(
+        &mut self,
+        id: String,
+        outline: &OutlineItem,
+        children: &mut Vec<(String, Vec<String>)>,
+    ) -> Result<(), CommandError> {
+        let (value, tags) = extract_tags(&outline.value);
+
+        match self.list.get_list_item(&id) {
+            Some(item) if item.value == value && tags.iter().all(|tag| item.has_tag(tag)) => {}
+            Some(_) => self.apply(Command::edit(self.list, &id, |item| {
+                item.value = value;
+
+                for tag in tags {
+                    if !item.has_tag(&tag) {
+                        item.tags.push(tag);
+                    }
+                }
+            })?)?,
+            None => {
+                let mut item = ListItem::new(value);
+                item.id = id.clone();
+                item.tags = tags;
+                self.apply(Command::InsertItem { item })?;
+            }
+        }
+
+        if children.iter().any(|(defined, _)| *defined == id) {
+            return Ok(());
+        }
+
+        let index = children.len();
+        children.push((id, Vec::new()));
+
+        for child in &outline.children {
+            let child_id = child.id.clone().unwrap_or_else(ListItem::new_id);
+            children[index].1.push(child_id.clone());
+            self.apply_outline_items(child_id, child, children)?;
+        }
+
+        Ok(())
+    }
+
+    /// Keeps the applied changes, returning them as a single command.
+    pub fn commit(mut self) -> Command {
+        Command::Batch(std::mem::take(&mut self.applied))
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        for command in self.applied.iter().rev() {
+            _ = command.inverse().apply(self.list);
+        }
+    }
+}
+
+/// Undo and redo stacks of the commands applied to a list.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct History {
+    undo_stack: Vec<Command>,
+    redo_stack: Vec<Command>,
+    /// Changes executed since [`History::begin_group`].
+    #[serde(skip)]
+    group: Option<Vec<Command>>,
+}
+
+impl History {
+    /// Oldest entries are dropped once the undo stack grows past this.
+    const MAX_ENTRIES: usize = 500;
+
+    pub fn new() -> History {
+        History::default()
+    }
+
+    /// Runs `build` in a transaction and records everything it applied as a
+    /// single undo step. Nothing is recorded or changed if `build` fails.
+    pub fn execute(
+        &mut self,
+        list: &mut List,
+        build: impl FnOnce(&mut Transaction) -> Result<(), CommandError>,
+    ) -> Result<(), CommandError> {
+        let mut transaction = Transaction::new(list);
+        build(&mut transaction)?;
+
+        let command = transaction.commit();
+        if command != Command::Batch(Vec::new()) {
+            match &mut self.group {
+                Some(group) => group.push(command),
+                None => self.record(command),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records every change executed until [`History::end_group`] as a single
+    /// undo step, like a change repeated several times.
+    pub fn begin_group(&mut self) {
+        self.group.get_or_insert_with(Vec::new);
+    }
+
+    pub fn end_group(&mut self) {
+        if let Some(group) = self.group.take()
+            && !group.is_empty()
+        {
+            self.record(Command::Batch(group));
+        }
+    }
+
+    fn record(&mut self, command: Command) {
+        self.undo_stack.push(command);
+        self.redo_stack.clear();
+
+        if self.undo_stack.len() > Self::MAX_ENTRIES {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    /// Applies a single command as its own undo step.
+    pub fn apply(&mut self, list: &mut List, command: Command) -> Result<(), CommandError> {
+        self.execute(list, |transaction| transaction.apply(command))
+    }
+
+    /// Reverts the most recent change. Returns `false` when there was nothing
+    /// to undo. A change that can no longer be reverted is discarded.
+    pub fn undo(&mut self, list: &mut List) -> Result<bool, CommandError> {
+        let Some(command) = self.undo_stack.pop() else {
+            return Ok(false);
+        };
+
+        command.inverse().apply(list)?;
+        self.redo_stack.push(command);
+
+        Ok(true)
+    }
+
+    /// Re-applies the most recently undone change. Returns `false` when there
+    /// was nothing to redo. A change that can no longer be applied is
+    /// discarded.
+    pub fn redo(&mut self, list: &mut List) -> Result<bool, CommandError> {
+        let Some(command) = self.redo_stack.pop() else {
+            return Ok(false);
+        };
+
+        command.apply(list)?;
+        self.undo_stack.push(command);
+
+        Ok(true)
+    }
+
+    pub fn into_string(&self) -> String {
+        serde_json::to_string(&self).unwrap()
+    }
+
+    pub fn try_from_str(serialized_string: &str) -> Result<History, LoadError> {
+        serde_json::from_str(serialized_string).map_err(LoadError::from)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub enum CommandError {
+    ItemDoesNotExist(String),
+    ItemAlreadyExists(String),
+    Task(ListItemTaskError),
+    Deletion(ListItemDeletionError),
+    /// The item appears in a single place, so there is nothing to unshare.
+    NotShared(String),
+}
+
+impl From<ListItemTaskError> for CommandError {
+    fn from(error: ListItemTaskError) -> Self {
+        CommandError::Task(error)
+    }
+}
+
+impl From<ListItemDeletionError> for CommandError {
+    fn from(error: ListItemDeletionError) -> Self {
+        CommandError::Deletion(error)
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::ItemDoesNotExist(id) => write!(f, "Item `{id}` does not exist"),
+            CommandError::ItemAlreadyExists(id) => write!(f, "Item `{id}` already exists"),
+            CommandError::Task(error) => write!(f, "{error}"),
+            CommandError::Deletion(error) => write!(f, "{error}"),
+            CommandError::NotShared(id) => write!(f, "Item `{id}` only appears in one place"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
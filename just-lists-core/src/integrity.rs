@@ -0,0 +1,238 @@
+use crate::list::List;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// A structural problem found in the item graph of a [`List`].
+#[derive(Debug, PartialEq)]
+pub enum IntegrityIssue {
+    DanglingTopLevelId(String),
+    DuplicateTopLevelId(String),
+    DanglingChildId { parent_id: String, child_id: String },
+    DuplicateChild { parent_id: String, child_id: String },
+    Cycle { parent_id: String, child_id: String },
+    OrphanedItem(String),
+}
+
+impl fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityIssue::DanglingTopLevelId(id) => {
+                write!(f, "Top level item `{id}` does not exist")
+            }
+            IntegrityIssue::DuplicateTopLevelId(id) => {
+                write!(f, "Top level item `{id}` is listed more than once")
+            }
+            IntegrityIssue::DanglingChildId {
+                parent_id,
+                child_id,
+            } => write!(
+                f,
+                "Item `{parent_id}` refers to child `{child_id}` which does not exist"
+            ),
+            IntegrityIssue::DuplicateChild {
+                parent_id,
+                child_id,
+            } => write!(
+                f,
+                "Item `{parent_id}` lists child `{child_id}` more than once"
+            ),
+            IntegrityIssue::Cycle {
+                parent_id,
+                child_id,
+            } => write!(
+                f,
+                "Item `{parent_id}` has child `{child_id}` which is also one of its ancestors"
+            ),
+            IntegrityIssue::OrphanedItem(id) => {
+                write!(f, "Item `{id}` is not reachable from any top level item")
+            }
+        }
+    }
+}
+
+impl List {
+    /// Reports every dangling reference, duplicate entry, cycle and orphaned
+    /// item in the list. An empty result means the list is consistent.
+    pub fn validate(&self) -> Vec<IntegrityIssue> {
+        let mut issues = Vec::new();
+
+        let mut seen = HashSet::new();
+        for id in self.top_level_items.iter() {
+            if !self.list_item_store.contains_key(id) {
+                issues.push(IntegrityIssue::DanglingTopLevelId(id.clone()));
+            } else if !seen.insert(id) {
+                issues.push(IntegrityIssue::DuplicateTopLevelId(id.clone()));
+            }
+        }
+
+        for parent_id in self.sorted_item_ids() {
+            let mut seen = HashSet::new();
+            for child_id in self.list_item_store[&parent_id].children.iter() {
+                if !self.list_item_store.contains_key(child_id) {
+                    issues.push(IntegrityIssue::DanglingChildId {
+                        parent_id: parent_id.clone(),
+                        child_id: child_id.clone(),
+                    });
+                } else if !seen.insert(child_id) {
+                    issues.push(IntegrityIssue::DuplicateChild {
+                        parent_id: parent_id.clone(),
+                        child_id: child_id.clone(),
+                    });
+                }
+            }
+        }
+
+        for (parent_id, child_id) in self.find_cycle_edges() {
+            issues.push(IntegrityIssue::Cycle {
+                parent_id,
+                child_id,
+            });
+        }
+
+        for id in self.find_orphans() {
+            issues.push(IntegrityIssue::OrphanedItem(id));
+        }
+
+        issues
+    }
+
+    /// Fixes the problems reported by [`List::validate`] and returns them.
+    ///
+    /// Dangling and duplicate references are dropped, cycles are broken by
+    /// removing the reference that closes them and orphaned items are moved
+    /// back to the top level so no data is lost.
+    pub fn repair(&mut self) -> Vec<IntegrityIssue> {
+        let issues = self.validate();
+
+        let mut seen = HashSet::new();
+        let store = &self.list_item_store;
+        self.top_level_items
+            .retain(|id| store.contains_key(id) && seen.insert(id.clone()));
+
+        let existing_ids: HashSet<String> = self.list_item_store.keys().cloned().collect();
+        for item in self.list_item_store.values_mut() {
+            let mut seen = HashSet::new();
+            item.children
+                .retain(|id| existing_ids.contains(id) && seen.insert(id.clone()));
+        }
+
+        for (parent_id, child_id) in self.find_cycle_edges() {
+            if let Some(parent) = self.list_item_store.get_mut(&parent_id) {
+                parent.children.retain(|c| *c != child_id);
+            }
+        }
+
+        let orphans = self.find_orphans();
+        let orphan_ids: HashSet<&String> = orphans.iter().collect();
+        let orphans_with_orphan_parent: HashSet<&String> = orphans
+            .iter()
+            .flat_map(|id| self.list_item_store[id].children.iter())
+            .filter(|id| orphan_ids.contains(id))
+            .collect();
+
+        let orphan_roots: Vec<String> = orphans
+            .iter()
+            .filter(|id| !orphans_with_orphan_parent.contains(id))
+            .cloned()
+            .collect();
+
+        self.top_level_items.extend(orphan_roots);
+
+        issues
+    }
+
+    fn sorted_item_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.list_item_store.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Depth first walk over every item, returning the edges that point back
+    /// at an item which is still being visited.
+    pub(crate) fn find_cycle_edges(&self) -> Vec<(String, String)> {
+        enum Visit {
+            InProgress,
+            Done,
+        }
+
+        let mut visits: HashMap<&str, Visit> = HashMap::new();
+        let mut cycle_edges = Vec::new();
+
+        let sorted_ids = self.sorted_item_ids();
+        let start_ids = self
+            .top_level_items
+            .iter()
+            .chain(sorted_ids.iter())
+            .filter(|id| self.list_item_store.contains_key(*id));
+
+        for start_id in start_ids {
+            if visits.contains_key(start_id.as_str()) {
+                continue;
+            }
+
+            let mut stack: Vec<(&str, usize)> = vec![(start_id, 0)];
+            visits.insert(start_id, Visit::InProgress);
+
+            while let Some((id, child_index)) = stack.pop() {
+                let children = &self.list_item_store[id].children;
+
+                match children.get(child_index) {
+                    None => {
+                        visits.insert(id, Visit::Done);
+                    }
+                    Some(child_id) => {
+                        stack.push((id, child_index + 1));
+
+                        if !self.list_item_store.contains_key(child_id) {
+                            continue;
+                        }
+
+                        match visits.get(child_id.as_str()) {
+                            Some(Visit::InProgress) => {
+                                let edge = (id.to_string(), child_id.clone());
+                                if !cycle_edges.contains(&edge) {
+                                    cycle_edges.push(edge);
+                                }
+                            }
+                            Some(Visit::Done) => (),
+                            None => {
+                                visits.insert(child_id, Visit::InProgress);
+                                stack.push((child_id, 0));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        cycle_edges
+    }
+
+    fn find_orphans(&self) -> Vec<String> {
+        let reachable = self.reachable_ids(self.top_level_items.iter().map(|id| id.as_str()));
+
+        self.sorted_item_ids()
+            .into_iter()
+            .filter(|id| !reachable.contains(id.as_str()))
+            .collect()
+    }
+
+    /// Every existing item reachable from `start_ids`, including themselves.
+    pub(crate) fn reachable_ids<'a>(
+        &'a self,
+        start_ids: impl Iterator<Item = &'a str>,
+    ) -> HashSet<&'a str> {
+        let mut reachable = HashSet::new();
+        let mut pending: Vec<&str> = start_ids.collect();
+
+        while let Some(id) = pending.pop() {
+            if let Some(item) = self.list_item_store.get(id)
+                && reachable.insert(item.id.as_str())
+            {
+                pending.extend(item.children.iter().map(|c| c.as_str()));
+            }
+        }
+
+        reachable
+    }
+}
@@ -1,5 +1,16 @@
+pub mod archive;
+pub mod dates;
+pub mod history;
+pub mod integrity;
 pub mod list;
 pub mod list_item;
+pub mod merge;
+pub mod migration;
+pub mod outline;
+pub mod search;
+pub mod storage;
+pub mod tags;
+pub mod workflow;
 
 use crate::list::List;
 use crate::list_item::ListItem;
@@ -32,10 +43,21 @@ pub fn get_sample_list() -> List {
 
 #[cfg(test)]
 mod tests {
+    use chrono::{DateTime, NaiveDate};
     use core::panic;
 
-    use crate::list::List;
-    use crate::list_item::ListItem;
+    use crate::dates::parse_date;
+    use crate::history::{Command, CommandError, History};
+    use crate::integrity::IntegrityIssue;
+    use crate::list::{List, ListItemDeletionError, ListItemTaskError, LoadError, Progress};
+    use crate::list_item::{ListItem, Priority, State};
+    use crate::merge::merge;
+    use crate::migration::CURRENT_FORMAT_VERSION;
+    use crate::outline::{OutlineError, parse_outline, write_outline};
+    use crate::search::{fuzzy_score, search};
+    use crate::storage::{Storage, StorageError, write_atomically};
+    use crate::tags::{extract_tags, parse_tag_list};
+    use crate::workflow::{StateDefinition, Workflow, WorkflowError};
 
     #[test]
     fn can_get_new_list() {
@@ -67,11 +89,1425 @@ mod tests {
         let list = super::get_sample_list();
 
         let serialized = list.into_string();
-        let deserialized: List = List::from_string(serialized);
+        let deserialized: List = List::try_from_str(&serialized).unwrap();
 
         println!("{:?}", deserialized);
     }
 
+    #[test]
+    fn empty_string_loads_as_empty_list() {
+        let list = List::try_from_str("  \n").unwrap();
+
+        assert!(list.get_top_level_list_items().is_empty());
+    }
+
+    #[test]
+    fn malformed_json_reports_position() {
+        match List::try_from_str("{\n  \"list_item_store\": {,\n}") {
+            Err(LoadError::MalformedJson { line, column, .. }) => {
+                assert_eq!(line, 2, "Unexpected line. Actual: {line}");
+                assert!(column > 0, "Expected a column. Actual: {column}");
+            }
+            other => panic!("Expected malformed JSON error. Actual: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn dangling_child_id_is_rejected() {
+        let serialized = r#"{
+            "list_item_store": {
+                "a": { "id": "a", "value": "A", "children": ["missing"], "state": "Pending" }
+            },
+            "top_level_items": ["a"]
+        }"#;
+
+        match List::try_from(serialized) {
+            Err(LoadError::DanglingChildId {
+                parent_id,
+                child_id,
+            }) => {
+                assert_eq!(parent_id, "a");
+                assert_eq!(child_id, "missing");
+            }
+            other => panic!("Expected dangling child error. Actual: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn missing_top_level_id_is_rejected() {
+        let serialized = r#"{ "list_item_store": {}, "top_level_items": ["missing"] }"#;
+
+        match List::try_from(serialized) {
+            Err(LoadError::MissingTopLevelId(id)) => assert_eq!(id, "missing"),
+            other => panic!("Expected missing top level error. Actual: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn cycle_is_rejected_and_repaired() {
+        let serialized = r#"{
+            "list_item_store": {
+                "a": { "id": "a", "value": "A", "children": ["b"], "state": "Pending" },
+                "b": { "id": "b", "value": "B", "children": ["a"], "state": "Pending" }
+            },
+            "top_level_items": ["a"]
+        }"#;
+
+        match List::try_from(serialized) {
+            Err(LoadError::Cycle {
+                parent_id,
+                child_id,
+            }) => {
+                assert_eq!(parent_id, "b");
+                assert_eq!(child_id, "a");
+            }
+            other => panic!("Expected cycle error. Actual: {:?}", other),
+        }
+
+        let mut list = List::try_from_str_unchecked(serialized).unwrap();
+        list.repair();
+        assert!(List::try_from_str(&list.into_string()).is_ok());
+    }
+
+    fn get_damaged_list() -> List {
+        // `a` lists `b` twice and a missing child, `b` points back at `a`, and
+        // `c` -> `d` are not reachable from any top level item.
+        let serialized = r#"{
+            "list_item_store": {
+                "a": { "id": "a", "value": "A", "children": ["b", "b", "missing"], "state": "Pending" },
+                "b": { "id": "b", "value": "B", "children": ["a"], "state": "Pending" },
+                "c": { "id": "c", "value": "C", "children": ["d"], "state": "Pending" },
+                "d": { "id": "d", "value": "D", "children": [], "state": "Pending" }
+            },
+            "top_level_items": ["a", "a", "gone"]
+        }"#;
+
+        List::try_from_str_unchecked(serialized).unwrap()
+    }
+
+    #[test]
+    fn sample_list_is_valid() {
+        let issues = super::get_sample_list().validate();
+
+        assert!(
+            issues.is_empty(),
+            "Expected no issues. Actual: {:?}",
+            issues
+        );
+    }
+
+    #[test]
+    fn validate_reports_every_issue() {
+        let issues = get_damaged_list().validate();
+
+        let expected = vec![
+            IntegrityIssue::DuplicateTopLevelId("a".to_string()),
+            IntegrityIssue::DanglingTopLevelId("gone".to_string()),
+            IntegrityIssue::DuplicateChild {
+                parent_id: "a".to_string(),
+                child_id: "b".to_string(),
+            },
+            IntegrityIssue::DanglingChildId {
+                parent_id: "a".to_string(),
+                child_id: "missing".to_string(),
+            },
+            IntegrityIssue::Cycle {
+                parent_id: "b".to_string(),
+                child_id: "a".to_string(),
+            },
+            IntegrityIssue::OrphanedItem("c".to_string()),
+            IntegrityIssue::OrphanedItem("d".to_string()),
+        ];
+
+        assert_eq!(issues, expected);
+    }
+
+    #[test]
+    fn repair_fixes_every_issue() {
+        let mut list = get_damaged_list();
+
+        let fixed = list.repair();
+        assert_eq!(
+            fixed.len(),
+            7,
+            "Unexpected fixed issues. Actual: {:?}",
+            fixed
+        );
+
+        let issues = list.validate();
+        assert!(
+            issues.is_empty(),
+            "Expected no issues. Actual: {:?}",
+            issues
+        );
+
+        let top_level_ids: Vec<&str> = list
+            .get_top_level_list_items()
+            .iter()
+            .map(|i| i.id.as_str())
+            .collect();
+        assert_eq!(top_level_ids, vec!["a", "c"]);
+
+        let a = list.get_list_item("a").unwrap();
+        assert_eq!(a.children, vec!["b".to_string()]);
+        assert!(list.get_list_item("b").unwrap().children.is_empty());
+    }
+
+    /// `project` and `daily` both reference `step`, which has a child `detail`.
+    fn get_shared_list() -> (List, String, String, String, String) {
+        let mut list = List::new();
+
+        let project = ListItem::new("Project".to_string());
+        let daily = ListItem::new("Daily".to_string());
+        let step = ListItem::new("Step".to_string());
+        let detail = ListItem::new("Detail".to_string());
+
+        let ids = (
+            project.id.clone(),
+            daily.id.clone(),
+            step.id.clone(),
+            detail.id.clone(),
+        );
+
+        list.add_list_item(project, None);
+        list.add_list_item(daily, None);
+        let _ = list.add_child_list_item(step, &ids.0, None);
+        let _ = list.add_child_list_item(detail, &ids.2, None);
+        let _ = list.add_existing_child_list_item(&ids.2, &ids.1, None);
+
+        (list, ids.0, ids.1, ids.2, ids.3)
+    }
+
+    #[test]
+    fn removing_top_level_item_cleans_top_level_items() {
+        let (mut list, project_id, daily_id, step_id, _) = get_shared_list();
+
+        list.remove_list_item(&project_id).unwrap();
+
+        let top_level_ids: Vec<&str> = list
+            .get_top_level_list_items()
+            .iter()
+            .map(|i| i.id.as_str())
+            .collect();
+        assert_eq!(top_level_ids, vec![daily_id.as_str()]);
+        assert!(list.validate().is_empty());
+
+        // `step` is still referenced by `daily` so it must survive.
+        assert!(list.get_list_item(&step_id).is_some());
+    }
+
+    #[test]
+    fn unlinking_shared_item_keeps_other_references() {
+        let (mut list, project_id, daily_id, step_id, detail_id) = get_shared_list();
+
+        assert_eq!(list.reference_count(&step_id), 2);
+
+        let removed = list
+            .remove_child_list_item(&step_id, Some(&project_id))
+            .unwrap();
+
+        assert!(removed.is_empty(), "Expected nothing to be deleted");
+        assert_eq!(list.reference_count(&step_id), 1);
+        assert_eq!(list.get_parent_ids(&step_id), vec![daily_id.as_str()]);
+        assert!(list.get_list_item(&detail_id).is_some());
+    }
+
+    #[test]
+    fn unlinking_last_reference_deletes_unreachable_descendants() {
+        let (mut list, project_id, daily_id, step_id, detail_id) = get_shared_list();
+
+        list.remove_child_list_item(&step_id, Some(&project_id))
+            .unwrap();
+        let removed = list
+            .remove_child_list_item(&step_id, Some(&daily_id))
+            .unwrap();
+
+        let mut removed_ids: Vec<String> = removed.into_iter().map(|i| i.id).collect();
+        removed_ids.sort();
+        let mut expected_ids = vec![step_id.clone(), detail_id.clone()];
+        expected_ids.sort();
+
+        assert_eq!(removed_ids, expected_ids);
+        assert!(list.get_list_item(&step_id).is_none());
+        assert!(list.get_list_item(&detail_id).is_none());
+        assert!(list.validate().is_empty());
+    }
+
+    #[test]
+    fn garbage_collection_keeps_descendants_shared_elsewhere() {
+        let (mut list, project_id, daily_id, step_id, detail_id) = get_shared_list();
+
+        // `detail` is also referenced directly by `daily`.
+        let _ = list.add_existing_child_list_item(&detail_id, &daily_id, None);
+
+        list.remove_child_list_item(&step_id, Some(&daily_id))
+            .unwrap();
+        let removed = list.remove_list_item(&project_id).unwrap();
+
+        let removed_ids: Vec<&str> = removed.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(
+            removed_ids.len(),
+            2,
+            "Unexpected removal: {:?}",
+            removed_ids
+        );
+        assert!(removed_ids.contains(&project_id.as_str()));
+        assert!(removed_ids.contains(&step_id.as_str()));
+        assert!(list.get_list_item(&detail_id).is_some());
+        assert!(list.validate().is_empty());
+    }
+
+    #[test]
+    fn removing_shared_item_everywhere_unlinks_all_parents() {
+        let (mut list, project_id, daily_id, step_id, detail_id) = get_shared_list();
+
+        list.remove_list_item(&step_id).unwrap();
+
+        assert!(list.get_list_item(&step_id).is_none());
+        assert!(list.get_list_item(&detail_id).is_none());
+        assert!(list.get_list_item(&project_id).unwrap().children.is_empty());
+        assert!(list.get_list_item(&daily_id).unwrap().children.is_empty());
+        assert!(list.validate().is_empty());
+    }
+
+    #[test]
+    fn unlinking_from_wrong_parent_is_an_error() {
+        let (mut list, _, daily_id, _, detail_id) = get_shared_list();
+
+        match list.remove_child_list_item(&detail_id, Some(&daily_id)) {
+            Err(ListItemDeletionError::ChildNotInParent) => (),
+            other => panic!("Expected ChildNotInParent. Actual: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn adding_item_under_its_descendant_is_refused() {
+        let (mut list, project_id, _, step_id, detail_id) = get_shared_list();
+
+        for parent_id in [&project_id, &step_id, &detail_id] {
+            match list.add_existing_child_list_item(&project_id, parent_id, None) {
+                Err(ListItemTaskError::WouldCreateCycle) => (),
+                other => panic!("Expected WouldCreateCycle. Actual: {:?}", other),
+            }
+        }
+
+        assert!(list.validate().is_empty());
+    }
+
+    #[test]
+    fn adding_item_under_a_sibling_branch_is_allowed() {
+        let (mut list, _, daily_id, _, detail_id) = get_shared_list();
+
+        list.add_existing_child_list_item(&detail_id, &daily_id, None)
+            .unwrap();
+
+        assert_eq!(list.reference_count(&detail_id), 2);
+        assert!(list.validate().is_empty());
+    }
+
+    fn snapshot(list: &List) -> serde_json::Value {
+        serde_json::to_value(list).unwrap()
+    }
+
+    #[test]
+    fn undo_and_redo_restore_every_step() {
+        let (mut list, project_id, daily_id, step_id, _) = get_shared_list();
+        let mut history = History::new();
+
+        let original = snapshot(&list);
+
+        let new_item = ListItem::new("New".to_string());
+        let command = Command::add_item(&list, new_item, Some(&daily_id), Some(0)).unwrap();
+        history.apply(&mut list, command).unwrap();
+        let after_add = snapshot(&list);
+
+        let command = Command::edit(&list, &step_id, |item| {
+            item.value = "Renamed".to_string();
+            item.state = State::Completed;
+        })
+        .unwrap();
+        history.apply(&mut list, command).unwrap();
+        let after_edit = snapshot(&list);
+
+        let command = Command::remove_everywhere(&list, &step_id).unwrap();
+        history.apply(&mut list, command).unwrap();
+        assert!(list.get_list_item(&step_id).is_none());
+
+        let command = Command::unlink(&list, &project_id, None).unwrap();
+        history.apply(&mut list, command).unwrap();
+        assert!(list.get_list_item(&project_id).is_none());
+
+        for _ in 0..2 {
+            assert!(history.undo(&mut list).unwrap());
+        }
+        assert_eq!(snapshot(&list), after_edit);
+
+        assert!(history.undo(&mut list).unwrap());
+        assert_eq!(snapshot(&list), after_add);
+
+        assert!(history.undo(&mut list).unwrap());
+        assert_eq!(snapshot(&list), original);
+        assert!(!history.undo(&mut list).unwrap());
+
+        for _ in 0..4 {
+            assert!(history.redo(&mut list).unwrap());
+        }
+        assert!(!history.redo(&mut list).unwrap());
+        assert!(list.get_list_item(&project_id).is_none());
+        assert!(list.validate().is_empty());
+    }
+
+    #[test]
+    fn transaction_records_a_move_as_one_step() {
+        let (mut list, project_id, daily_id, step_id, _) = get_shared_list();
+        let mut history = History::new();
+
+        list.remove_child_list_item(&step_id, Some(&daily_id))
+            .unwrap();
+        let original = snapshot(&list);
+
+        history
+            .execute(&mut list, |transaction| {
+                let link = Command::link(transaction.list(), &step_id, Some(&daily_id), None)?;
+                transaction.apply(link)?;
+                let unlink = Command::unlink(transaction.list(), &step_id, Some(&project_id))?;
+                transaction.apply(unlink)
+            })
+            .unwrap();
+
+        assert_eq!(list.get_parent_ids(&step_id), vec![daily_id.as_str()]);
+
+        history.undo(&mut list).unwrap();
+        assert_eq!(snapshot(&list), original);
+    }
+
+    #[test]
+    fn grouped_changes_are_undone_in_one_step() {
+        let (mut list, project_id, _, _, _) = get_shared_list();
+        let mut history = History::new();
+
+        let command = Command::edit(&list, &project_id, |item| {
+            item.value = "Renamed".to_string();
+        })
+        .unwrap();
+        history.apply(&mut list, command).unwrap();
+        let before_group = snapshot(&list);
+
+        history.begin_group();
+        for value in ["First", "Second", "Third"] {
+            let command = Command::add_item(&list, ListItem::new(value.to_string()), None, None);
+            history.apply(&mut list, command.unwrap()).unwrap();
+        }
+        history.end_group();
+        let after_group = snapshot(&list);
+
+        assert!(history.undo(&mut list).unwrap());
+        assert_eq!(snapshot(&list), before_group);
+
+        assert!(history.redo(&mut list).unwrap());
+        assert_eq!(snapshot(&list), after_group);
+
+        assert!(history.undo(&mut list).unwrap());
+        assert!(history.undo(&mut list).unwrap());
+        assert!(!history.can_undo());
+
+        // An empty group records nothing.
+        history.begin_group();
+        history.end_group();
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn failed_transaction_changes_nothing() {
+        let (mut list, project_id, _, step_id, _) = get_shared_list();
+        let mut history = History::new();
+
+        let original = snapshot(&list);
+
+        let result = history.execute(&mut list, |transaction| {
+            let edit = Command::edit(transaction.list(), &project_id, |item| {
+                item.value = "Changed".to_string();
+            })?;
+            transaction.apply(edit)?;
+            let link = Command::link(transaction.list(), &project_id, Some(&step_id), None)?;
+            transaction.apply(link)
+        });
+
+        match result {
+            Err(CommandError::Task(ListItemTaskError::WouldCreateCycle)) => (),
+            other => panic!("Expected WouldCreateCycle. Actual: {:?}", other),
+        }
+
+        assert_eq!(snapshot(&list), original);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn history_can_be_persisted() {
+        let (mut list, project_id, _, _, _) = get_shared_list();
+        let mut history = History::new();
+
+        let command = Command::unlink(&list, &project_id, None).unwrap();
+        history.apply(&mut list, command).unwrap();
+
+        let serialized = history.into_string();
+        let mut restored = History::try_from_str(&serialized).unwrap();
+
+        assert!(restored.undo(&mut list).unwrap());
+        assert!(list.get_list_item(&project_id).is_some());
+        assert!(list.validate().is_empty());
+    }
+
+    #[test]
+    fn storage_ignores_history_saved_with_another_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("list.json");
+        let mut storage = Storage::new(path.clone());
+        let mut list = storage.load().unwrap();
+        let mut history = History::new();
+
+        let item = ListItem::new("kept".to_string());
+        let command = Command::add_item(&list, item, None, None).unwrap();
+        history.apply(&mut list, command).unwrap();
+        storage.save(&list).unwrap();
+        storage.save_history(&history).unwrap();
+
+        let mut storage = Storage::new(path.clone());
+        storage.load().unwrap();
+        assert!(storage.load_history().unwrap().is_some());
+
+        let mut other = list.clone();
+        other.add_list_item(ListItem::new("changed elsewhere".to_string()), None);
+        std::fs::write(&path, other.into_string()).unwrap();
+
+        let mut storage = Storage::new(path);
+        storage.load().unwrap();
+        assert!(storage.load_history().unwrap().is_none());
+    }
+
+    #[test]
+    fn storage_loads_missing_file_as_empty_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = Storage::new(dir.path().join("list.json"));
+
+        let list = storage.load().unwrap();
+
+        assert!(list.get_top_level_list_items().is_empty());
+    }
+
+    #[test]
+    fn storage_save_round_trips_and_rotates_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("list.json");
+        let mut list = List::new();
+
+        // Every session backs up the file once, however often it saves.
+        for session in [["first", "second"], ["third", "fourth"], ["fifth", "sixth"]] {
+            let mut storage = Storage::new(path.clone()).with_backup_count(2);
+            storage.load().unwrap();
+
+            for value in session {
+                list.add_list_item(ListItem::new(value.to_string()), None);
+                storage.save(&list).unwrap();
+            }
+        }
+
+        let mut storage = Storage::new(path).with_backup_count(2);
+        assert_eq!(storage.load().unwrap().get_top_level_list_items().len(), 6);
+
+        let backup = std::fs::read_to_string(storage.backup_path(0)).unwrap();
+        assert_eq!(
+            List::try_from_str(&backup).unwrap().top_level_items.len(),
+            4
+        );
+
+        let older_backup = std::fs::read_to_string(storage.backup_path(1)).unwrap();
+        assert_eq!(
+            List::try_from_str(&older_backup)
+                .unwrap()
+                .top_level_items
+                .len(),
+            2
+        );
+
+        assert!(!storage.backup_path(2).exists());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn storage_reports_damaged_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = Storage::new(dir.path().join("list.json"));
+        std::fs::write(storage.path(), "{ not json").unwrap();
+
+        assert!(matches!(
+            storage.load(),
+            Err(StorageError::Load(LoadError::MalformedJson { .. }))
+        ));
+    }
+
+    #[test]
+    fn saving_a_restored_backup_keeps_the_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = Storage::new(dir.path().join("list.json"));
+        let mut list = List::new();
+        list.add_list_item(ListItem::new("good".to_string()), None);
+        std::fs::write(storage.backup_path(0), list.into_string()).unwrap();
+        std::fs::write(storage.path(), "{ not json").unwrap();
+
+        assert!(storage.load().is_err());
+        let mut list = storage.load_backup(0).unwrap();
+        list.add_list_item(ListItem::new("new".to_string()), None);
+        storage.save(&list).unwrap();
+
+        let backup = std::fs::read_to_string(storage.backup_path(0)).unwrap();
+        assert_eq!(
+            List::try_from_str(&backup).unwrap().top_level_items.len(),
+            1
+        );
+        assert!(!storage.backup_path(1).exists());
+    }
+
+    #[test]
+    fn failed_write_cleans_up_temporary_file() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // Renaming a file over a non-empty directory fails after the
+        // temporary file was written.
+        let target = dir.path().join("directory");
+        std::fs::create_dir(&target).unwrap();
+        std::fs::write(target.join("child"), "").unwrap();
+
+        assert!(write_atomically(&target, b"new").is_err());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        assert!(target.join("child").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_keeps_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("list.json");
+        std::fs::write(&path, "old").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+        write_atomically(&path, b"new").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640, "Unexpected mode. Actual: {mode:o}");
+    }
+
+    #[test]
+    fn storage_refuses_to_save_over_outside_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = Storage::new(dir.path().join("list.json"));
+        let mut list = storage.load().unwrap();
+
+        list.add_list_item(ListItem::new("ours".to_string()), None);
+        storage.save(&list).unwrap();
+        assert!(!storage.has_changed_on_disk().unwrap());
+
+        std::fs::write(storage.path(), List::new().into_string()).unwrap();
+        assert!(storage.has_changed_on_disk().unwrap());
+        assert!(matches!(storage.save(&list), Err(StorageError::Conflict)));
+
+        storage.overwrite(&list).unwrap();
+        assert_eq!(storage.load().unwrap().get_top_level_list_items().len(), 1);
+    }
+
+    #[test]
+    fn lock_is_exclusive_and_released_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path().join("list.json"));
+
+        let lock = storage.lock().unwrap();
+        assert!(lock.path().exists());
+        assert!(matches!(
+            storage.lock(),
+            Err(StorageError::Locked { pid }) if pid == std::process::id()
+        ));
+
+        drop(lock);
+        assert!(!storage.sibling_path(".lock").exists());
+        assert!(storage.lock().is_ok());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn stale_lock_is_taken_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path().join("list.json"));
+        std::fs::write(storage.sibling_path(".lock"), u32::MAX.to_string()).unwrap();
+
+        assert!(storage.lock().is_ok());
+    }
+
+    #[test]
+    fn merge_combines_changes_from_both_sides() {
+        let (base, project_id, daily_id, step_id, detail_id) = get_shared_list();
+
+        let mut ours = base.clone();
+        ours.get_mut_list_item(&project_id).unwrap().value = "Renamed".to_string();
+        let our_item = ListItem::new("Added by us".to_string());
+        let our_item_id = our_item.id.clone();
+        ours.add_child_list_item(our_item, &project_id, None)
+            .unwrap();
+
+        let mut theirs = base.clone();
+        theirs.get_mut_list_item(&project_id).unwrap().state = State::Completed;
+        let their_item = ListItem::new("Added by them".to_string());
+        let their_item_id = their_item.id.clone();
+        theirs
+            .add_child_list_item(their_item, &project_id, Some(0))
+            .unwrap();
+        theirs.remove_list_item(&detail_id).unwrap();
+
+        let result = merge(&base, &ours, &theirs);
+        let project = result.list.get_list_item(&project_id).unwrap();
+
+        assert!(result.conflicts.is_empty());
+        assert_eq!(project.value, "Renamed");
+        assert_eq!(project.state, State::Completed);
+        assert_eq!(
+            project.children,
+            vec![their_item_id, step_id.clone(), our_item_id]
+        );
+        assert!(result.list.get_list_item(&detail_id).is_none());
+        assert!(result.list.get_list_item(&daily_id).is_some());
+        assert!(result.list.validate().is_empty());
+    }
+
+    #[test]
+    fn merge_reports_conflicting_edits_and_keeps_ours() {
+        let (base, project_id, _, step_id, _) = get_shared_list();
+
+        let mut ours = base.clone();
+        ours.get_mut_list_item(&project_id).unwrap().value = "Ours".to_string();
+
+        let mut theirs = base.clone();
+        theirs.get_mut_list_item(&project_id).unwrap().value = "Theirs".to_string();
+        theirs.get_mut_list_item(&step_id).unwrap().value = "Edited step".to_string();
+        ours.remove_list_item(&step_id).unwrap();
+
+        let result = merge(&base, &ours, &theirs);
+
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].item_id, project_id);
+        assert_eq!(result.conflicts[0].field, "value");
+        assert_eq!(
+            result.list.get_list_item(&project_id).unwrap().value,
+            "Ours"
+        );
+        // An item deleted on one side but edited on the other is kept.
+        assert!(result.list.get_list_item(&step_id).is_some());
+        assert!(result.list.validate().is_empty());
+    }
+
+    /// A list saved by every format version so far, all holding the same
+    /// items (apart from the states that version 0 files may lack). Each one
+    /// adds what its version introduced, so the last sets every optional
+    /// field.
+    const FORMAT_FIXTURES: [(&str, &str); 9] = [
+        (
+            "v0_without_state",
+            include_str!("../tests/fixtures/v0_without_state.json"),
+        ),
+        (
+            "v0_with_state",
+            include_str!("../tests/fixtures/v0_with_state.json"),
+        ),
+        ("v1", include_str!("../tests/fixtures/v1.json")),
+        ("v2", include_str!("../tests/fixtures/v2.json")),
+        ("v3", include_str!("../tests/fixtures/v3.json")),
+        ("v4", include_str!("../tests/fixtures/v4.json")),
+        ("v5", include_str!("../tests/fixtures/v5.json")),
+        ("v6", include_str!("../tests/fixtures/v6.json")),
+        ("v7", include_str!("../tests/fixtures/v7.json")),
+    ];
+
+    #[test]
+    fn lists_from_every_format_version_load() {
+        for (name, content) in FORMAT_FIXTURES {
+            let list = List::try_from_str(content)
+                .unwrap_or_else(|e| panic!("Unable to load fixture {name}: {e}"));
+
+            assert!(list.validate().is_empty(), "Fixture {name} is not valid");
+
+            let values: Vec<&str> = list
+                .get_top_level_list_items()
+                .iter()
+                .map(|i| i.value.as_str())
+                .collect();
+            assert_eq!(values, vec!["Groceries", "Chores"], "Fixture {name}");
+
+            let groceries = list.get_list_item("groceries").unwrap();
+            assert_eq!(list.get_children(groceries).len(), 2, "Fixture {name}");
+            assert_eq!(list.reference_count("bread"), 2, "Fixture {name}");
+            assert_eq!(
+                list.get_list_item("bread").unwrap().state,
+                State::Pending,
+                "Fixture {name}"
+            );
+        }
+    }
+
+    #[test]
+    fn migrated_list_is_saved_in_current_format() {
+        let (_, content) = FORMAT_FIXTURES[0];
+        let list = List::try_from_str(content).unwrap();
+
+        let saved: serde_json::Value = serde_json::from_str(&list.into_string()).unwrap();
+
+        assert_eq!(saved["format_version"], CURRENT_FORMAT_VERSION);
+        assert_eq!(saved["list_item_store"]["milk"]["state"], "Pending");
+        assert_eq!(
+            snapshot(&List::try_from_str(&list.into_string()).unwrap()),
+            snapshot(&list)
+        );
+    }
+
+    #[test]
+    fn newer_format_version_is_rejected() {
+        let serialized = format!(
+            r#"{{"format_version": {}, "list_item_store": {{}}, "top_level_items": []}}"#,
+            CURRENT_FORMAT_VERSION + 1
+        );
+
+        assert!(matches!(
+            List::try_from_str(&serialized),
+            Err(LoadError::UnsupportedVersion(version)) if version == CURRENT_FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn optional_fields_round_trip_and_are_saved_only_when_set() {
+        let (_, content) = FORMAT_FIXTURES[8];
+        let list = List::try_from_str(content).unwrap();
+        let saved: serde_json::Value = serde_json::from_str(&list.into_string()).unwrap();
+
+        // The field, an item setting it with its value, and an item without it.
+        let fields = [
+            ("due", "groceries", serde_json::json!("2025-03-01"), "milk"),
+            (
+                "scheduled",
+                "bread",
+                serde_json::json!("2025-02-27"),
+                "milk",
+            ),
+            (
+                "tags",
+                "milk",
+                serde_json::json!(["dairy", "errand"]),
+                "bread",
+            ),
+            ("priority", "chores", serde_json::json!("Urgent"), "bread"),
+            (
+                "notes",
+                "groceries",
+                serde_json::json!("Use the **corner shop**.\n\n- Bring bags"),
+                "bread",
+            ),
+        ];
+
+        for (field, set_id, value, unset_id) in fields {
+            assert_eq!(saved["list_item_store"][set_id][field], value, "{field}");
+            assert!(
+                saved["list_item_store"][unset_id].get(field).is_none(),
+                "{field}"
+            );
+        }
+
+        assert_eq!(
+            list.get_list_item("groceries").unwrap().due,
+            NaiveDate::from_ymd_opt(2025, 3, 1)
+        );
+        assert_eq!(
+            list.get_list_item("chores").unwrap().priority,
+            Priority::Urgent
+        );
+
+        let (_, content) = FORMAT_FIXTURES[2];
+        let saved = List::try_from_str(content).unwrap().into_string();
+        assert!(!saved.contains("archive"));
+    }
+
+    #[test]
+    fn overdue_and_due_today_ignore_completed_items() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 5).unwrap();
+        let workflow = Workflow::default();
+        let mut item = ListItem::new("Report".to_string());
+
+        assert!(!item.is_overdue(today, &workflow));
+
+        item.due = NaiveDate::from_ymd_opt(2025, 3, 4);
+        assert!(item.is_overdue(today, &workflow));
+        assert!(!item.is_due_today(today, &workflow));
+
+        item.due = Some(today);
+        assert!(!item.is_overdue(today, &workflow));
+        assert!(item.is_due_today(today, &workflow));
+
+        item.state = State::Completed;
+        assert!(!item.is_due_today(today, &workflow));
+    }
+
+    #[test]
+    fn progress_counts_shared_descendants_once() {
+        let (mut list, project_id, daily_id, step_id, detail_id) = get_shared_list();
+        let workflow = Workflow::default();
+
+        let extra = ListItem::new("Extra".to_string());
+        let extra_id = extra.id.clone();
+        list.add_child_list_item(extra, &project_id, None).unwrap();
+        // `detail` is now reachable from `project` twice.
+        list.add_existing_child_list_item(&detail_id, &project_id, None)
+            .unwrap();
+        list.get_mut_list_item(&detail_id).unwrap().state = State::Completed;
+
+        assert_eq!(
+            list.progress(&project_id, &workflow),
+            Some(Progress { done: 1, total: 3 })
+        );
+        assert_eq!(
+            list.progress(&daily_id, &workflow),
+            Some(Progress { done: 1, total: 2 })
+        );
+        assert_eq!(list.progress(&extra_id, &workflow), None);
+        assert!(list.children_done(&step_id, &workflow));
+        assert!(!list.children_done(&project_id, &workflow));
+    }
+
+    #[test]
+    fn finished_ancestors_are_completed_in_one_undo_step() {
+        let (mut list, project_id, daily_id, step_id, detail_id) = get_shared_list();
+        let workflow = Workflow::default();
+        let mut history = History::new();
+
+        let extra = ListItem::new("Extra".to_string());
+        list.add_child_list_item(extra, &project_id, None).unwrap();
+
+        history
+            .execute(&mut list, |transaction| {
+                transaction.apply(Command::edit(transaction.list(), &detail_id, |item| {
+                    item.state = State::Completed
+                })?)?;
+                transaction.complete_finished_ancestors(&detail_id, &workflow)
+            })
+            .unwrap();
+
+        let state = |list: &List, id: &str| list.get_list_item(id).unwrap().state.clone();
+        assert_eq!(state(&list, &step_id), State::Completed);
+        assert_eq!(state(&list, &daily_id), State::Completed);
+        // `project` still has an open child.
+        assert_eq!(state(&list, &project_id), State::Pending);
+
+        history.undo(&mut list).unwrap();
+        assert_eq!(state(&list, &detail_id), State::Pending);
+        assert_eq!(state(&list, &step_id), State::Pending);
+        assert_eq!(state(&list, &daily_id), State::Pending);
+    }
+
+    #[test]
+    fn fuzzy_search_prefers_word_starts_and_runs() {
+        assert!(fuzzy_score("grc", "Groceries").is_some());
+        assert!(fuzzy_score("GRO", "groceries").is_some());
+        assert_eq!(fuzzy_score("xyz", "Groceries"), None);
+        assert_eq!(fuzzy_score("sg", "Groceries"), None);
+        assert!(fuzzy_score("milk", "Buy milk") > fuzzy_score("milk", "Make it look"));
+    }
+
+    #[test]
+    fn search_reports_shared_items_once_with_their_path() {
+        let (list, project_id, _, step_id, detail_id) = get_shared_list();
+
+        let results = search(&list, "detail");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id_path, vec![project_id, step_id, detail_id]);
+
+        assert_eq!(search(&list, "t").len(), 3);
+        assert!(search(&list, "  ").is_empty());
+    }
+
+    #[test]
+    fn search_finds_text_in_notes_after_values() {
+        let (mut list, project_id, daily_id, _, _) = get_shared_list();
+        list.get_mut_list_item(&daily_id).unwrap().notes =
+            Some("Check the *project* board".to_string());
+        list.get_mut_list_item(&project_id).unwrap().notes = Some("Board".to_string());
+
+        let results = search(&list, "project");
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].id_path, vec![project_id.clone()]);
+        assert!(!results[0].in_notes);
+        assert_eq!(results[1].id_path, vec![daily_id.clone()]);
+        assert!(results[1].in_notes);
+
+        // Notes have to contain the query, not just its letters in order.
+        assert!(search(&list, "cpb").is_empty());
+    }
+
+    #[test]
+    fn archived_items_can_be_restored_where_they_were() {
+        let (mut list, project_id, daily_id, step_id, detail_id) = get_shared_list();
+        let mut history = History::new();
+        let archived_at = DateTime::from_timestamp(1_740_000_000, 0).unwrap();
+        let before = snapshot(&list);
+
+        let command = Command::archive(&list, &step_id, Some(&project_id), archived_at).unwrap();
+        history.apply(&mut list, command).unwrap();
+
+        // `step` is still shared with `daily`, so only this occurrence goes.
+        assert!(
+            list.get_children(list.get_list_item(&project_id).unwrap())
+                .is_empty()
+        );
+        assert_eq!(list.reference_count(&step_id), 1);
+        let entry = list.archived_items()[0].clone();
+        assert_eq!(entry.item.id, step_id);
+        assert_eq!(entry.descendants[0].id, detail_id);
+        assert_eq!(entry.parent_id.as_deref(), Some(project_id.as_str()));
+
+        history.undo(&mut list).unwrap();
+        assert_eq!(snapshot(&list), before);
+        assert!(list.archived_items().is_empty());
+        history.redo(&mut list).unwrap();
+
+        // Once the other reference is gone too, restoring brings it all back.
+        let command = Command::unlink(&list, &step_id, Some(&daily_id)).unwrap();
+        history.apply(&mut list, command).unwrap();
+        assert!(list.get_list_item(&detail_id).is_none());
+
+        let command = Command::restore(&list, &entry).unwrap();
+        history.apply(&mut list, command).unwrap();
+
+        assert_eq!(list.get_parent_ids(&step_id), vec![project_id.as_str()]);
+        assert!(list.get_list_item(&detail_id).is_some());
+        assert!(list.archived_items().is_empty());
+        assert!(list.validate().is_empty());
+    }
+
+    #[test]
+    fn archived_items_whose_parent_is_gone_are_restored_at_the_top_level() {
+        let (_, content) = FORMAT_FIXTURES[7];
+        let mut list = List::try_from_str(content).unwrap();
+        let entry = list.archived_items()[0].clone();
+
+        list.remove_list_item("groceries").unwrap();
+        Command::restore(&list, &entry)
+            .unwrap()
+            .apply(&mut list)
+            .unwrap();
+
+        let values: Vec<&str> = list
+            .get_top_level_list_items()
+            .iter()
+            .map(|i| i.value.as_str())
+            .collect();
+        assert_eq!(values, vec!["Chores", "Eggs"]);
+        assert!(list.validate().is_empty());
+    }
+
+    #[test]
+    fn merge_keeps_items_archived_on_either_side() {
+        let (base, project_id, daily_id, _, _) = get_shared_list();
+        let archived_at = DateTime::from_timestamp(1_740_000_000, 0).unwrap();
+
+        let mut ours = base.clone();
+        Command::archive(&ours, &project_id, None, archived_at)
+            .unwrap()
+            .apply(&mut ours)
+            .unwrap();
+
+        let mut theirs = base.clone();
+        Command::archive(&theirs, &daily_id, None, archived_at)
+            .unwrap()
+            .apply(&mut theirs)
+            .unwrap();
+
+        let result = merge(&base, &ours, &theirs);
+
+        let archived: Vec<&str> = result
+            .list
+            .archived_items()
+            .iter()
+            .map(|entry| entry.item.id.as_str())
+            .collect();
+        assert_eq!(archived, vec![project_id.as_str(), daily_id.as_str()]);
+        assert!(result.list.get_list_item(&project_id).is_none());
+        assert!(result.list.validate().is_empty());
+    }
+
+    #[test]
+    fn items_can_be_moved_between_and_within_parents() {
+        let (mut list, project_id, daily_id, step_id, _) = get_shared_list();
+        let extra = ListItem::new("Extra".to_string());
+        let extra_id = extra.id.clone();
+        list.add_child_list_item(extra, &daily_id, None).unwrap();
+
+        list.move_item(&extra_id, Some(&daily_id), Some(&daily_id), 0)
+            .unwrap();
+        assert_eq!(
+            list.get_list_item(&daily_id).unwrap().children,
+            vec![extra_id.clone(), step_id.clone()]
+        );
+
+        // Outdent to the top level, right after `daily`.
+        list.move_item(&extra_id, Some(&daily_id), None, 2).unwrap();
+        assert_eq!(
+            list.top_level_items,
+            vec![project_id.clone(), daily_id.clone(), extra_id.clone()]
+        );
+
+        // Indent under `project`, the same as paste but at an exact index.
+        list.move_item(&extra_id, None, Some(&project_id), 0)
+            .unwrap();
+        assert_eq!(
+            list.get_list_item(&project_id).unwrap().children,
+            vec![extra_id.clone(), step_id.clone()]
+        );
+        assert!(list.validate().is_empty());
+
+        assert!(matches!(
+            list.move_item(&project_id, None, Some(&step_id), 0),
+            Err(ListItemTaskError::WouldCreateCycle)
+        ));
+        assert!(matches!(
+            list.move_item(&step_id, Some(&project_id), Some(&daily_id), 0),
+            Err(ListItemTaskError::ParentAlreadyHasItem)
+        ));
+        assert!(matches!(
+            list.move_item(&step_id, None, Some(&daily_id), 0),
+            Err(ListItemTaskError::ChildIdDoesNotExist)
+        ));
+    }
+
+    #[test]
+    fn moving_an_item_can_be_undone() {
+        let (mut list, project_id, daily_id, _, _) = get_shared_list();
+        let mut history = History::new();
+        let before = snapshot(&list);
+
+        let command = Command::move_item(&list, &daily_id, None, None, 0).unwrap();
+        history.apply(&mut list, command).unwrap();
+        assert_eq!(list.top_level_items, vec![daily_id, project_id]);
+
+        history.undo(&mut list).unwrap();
+        assert_eq!(snapshot(&list), before);
+    }
+
+    #[test]
+    fn deep_copies_get_fresh_ids_and_keep_inner_sharing() {
+        let (mut list, project_id, _, step_id, detail_id) = get_shared_list();
+        // `detail` is shared within `project`'s subtree.
+        list.add_existing_child_list_item(&detail_id, &project_id, None)
+            .unwrap();
+
+        let copies = list.deep_clone_subtree(&project_id).unwrap();
+        assert_eq!(copies.len(), 3);
+        assert!(
+            copies
+                .iter()
+                .all(|copy| list.get_list_item(&copy.id).is_none())
+        );
+
+        let (copy_step_id, copy_detail_id) = (&copies[1].id, &copies[2].id);
+        assert_eq!(copies[1].value, "Step");
+        assert_eq!(
+            copies[0].children,
+            vec![copy_step_id.clone(), copy_detail_id.clone()]
+        );
+        assert_eq!(copies[1].children, vec![copy_detail_id.clone()]);
+
+        let command = Command::add_copy(&list, &step_id, None, Some(0)).unwrap();
+        command.apply(&mut list).unwrap();
+
+        let copy = list.get_top_level_list_items()[0].clone();
+        assert_ne!(copy.id, step_id);
+        assert_eq!(copy.value, "Step");
+        list.get_mut_list_item(&list.get_children(&copy)[0].id.clone())
+            .unwrap()
+            .value = "Changed".to_string();
+        assert_eq!(list.get_list_item(&detail_id).unwrap().value, "Detail");
+        assert!(list.validate().is_empty());
+        assert_eq!(list.deep_clone_subtree("missing"), None);
+    }
+
+    #[test]
+    fn locations_list_every_path_to_a_shared_item() {
+        let (list, project_id, daily_id, step_id, detail_id) = get_shared_list();
+
+        let mut expected = vec![
+            vec![project_id.clone(), step_id.clone(), detail_id.clone()],
+            vec![daily_id.clone(), step_id.clone(), detail_id.clone()],
+        ];
+        expected.sort();
+        assert_eq!(list.get_locations(&detail_id), expected);
+        assert_eq!(
+            list.get_locations(&project_id),
+            vec![vec![project_id.clone()]]
+        );
+
+        let counts = list.reference_counts();
+        assert_eq!(counts[step_id.as_str()], 2);
+        assert_eq!(counts[detail_id.as_str()], 1);
+        assert_eq!(
+            counts[project_id.as_str()],
+            list.reference_count(&project_id)
+        );
+    }
+
+    #[test]
+    fn unsharing_replaces_one_occurrence_with_a_copy() {
+        let (mut list, project_id, daily_id, step_id, detail_id) = get_shared_list();
+        let mut history = History::new();
+        let before = snapshot(&list);
+
+        let command = Command::unshare(&list, &step_id, Some(&daily_id)).unwrap();
+        history.apply(&mut list, command).unwrap();
+
+        assert_eq!(list.reference_count(&step_id), 1);
+        assert_eq!(list.get_parent_ids(&step_id), vec![project_id.as_str()]);
+
+        let copy = list.get_children(list.get_list_item(&daily_id).unwrap())[0];
+        assert_ne!(copy.id, step_id);
+        assert_eq!(copy.value, "Step");
+        assert_ne!(copy.children, vec![detail_id.clone()]);
+        assert!(list.validate().is_empty());
+
+        assert!(matches!(
+            Command::unshare(&list, &step_id, Some(&project_id)),
+            Err(CommandError::NotShared(_))
+        ));
+
+        history.undo(&mut list).unwrap();
+        assert_eq!(snapshot(&list), before);
+    }
+
+    #[test]
+    fn outline_round_trips_without_changes() {
+        let (mut list, project_id, daily_id, step_id, detail_id) = get_shared_list();
+        let _ = list.add_existing_child_list_item(&daily_id, &project_id, None);
+        list.top_level_items.retain(|id| *id != daily_id);
+        list.get_mut_list_item(&detail_id).unwrap().value = "Two\nlines \\ slash".to_string();
+        let mut history = History::new();
+        let before = snapshot(&list);
+
+        let text = write_outline(&list, &project_id).unwrap();
+        assert_eq!(
+            text,
+            format!(
+                "Project <!-- {project_id} -->\n  Step <!-- {step_id} -->\n    Two\\nlines \\\\ slash <!-- {detail_id} -->\n  Daily <!-- {daily_id} -->\n    Step <!-- {step_id} -->\n"
+            )
+        );
+
+        let outline = parse_outline(&text).unwrap();
+        outline.check(&list, &project_id).unwrap();
+        history
+            .execute(&mut list, |transaction| {
+                transaction.apply_outline(&project_id, &outline)
+            })
+            .unwrap();
+
+        assert_eq!(snapshot(&list), before);
+    }
+
+    #[test]
+    fn outline_edits_adds_removes_and_moves_items() {
+        let (mut list, project_id, daily_id, step_id, detail_id) = get_shared_list();
+        let old = ListItem::new("Old".to_string());
+        let old_id = old.id.clone();
+        let _ = list.add_child_list_item(old, &project_id, None);
+        let mut history = History::new();
+        let before = snapshot(&list);
+
+        let text = format!(
+            "Renamed #work <!-- {project_id} -->\n\n\tNew item\n\t\tNew child\n\tDetail <!-- {detail_id} -->\n\tStep <!-- {step_id} -->\n"
+        );
+        let outline = parse_outline(&text).unwrap();
+        outline.check(&list, &project_id).unwrap();
+        history
+            .execute(&mut list, |transaction| {
+                transaction.apply_outline(&project_id, &outline)
+            })
+            .unwrap();
+
+        let project = list.get_list_item(&project_id).unwrap();
+        assert_eq!(project.value, "Renamed");
+        assert_eq!(project.tags, vec!["work".to_string()]);
+
+        let children = list.get_children(project);
+        let values: Vec<&str> = children.iter().map(|c| c.value.as_str()).collect();
+        assert_eq!(values, vec!["New item", "Detail", "Step"]);
+        assert_eq!(list.get_children(children[0])[0].value, "New child");
+
+        // `step` is shared, so losing its children here loses them in `daily`.
+        assert!(list.get_list_item(&step_id).unwrap().children.is_empty());
+        assert_eq!(list.get_parent_ids(&step_id).len(), 2);
+        assert!(list.get_parent_ids(&daily_id).is_empty());
+        assert!(list.get_list_item(&old_id).is_none());
+        assert!(list.validate().is_empty());
+
+        history.undo(&mut list).unwrap();
+        assert_eq!(snapshot(&list), before);
+    }
+
+    #[test]
+    fn invalid_outlines_are_rejected() {
+        let (list, project_id, daily_id, step_id, _) = get_shared_list();
+
+        assert_eq!(parse_outline("\n  \n"), Err(OutlineError::Empty));
+        assert_eq!(
+            parse_outline("  One\n    Two\n  Three"),
+            Err(OutlineError::SeveralRoots { line: 3 })
+        );
+
+        let check = |text: String| parse_outline(&text).unwrap().check(&list, &project_id);
+
+        assert_eq!(
+            check(format!("Daily <!-- {daily_id} -->")),
+            Err(OutlineError::NotTheOutlinedItem)
+        );
+        assert_eq!(
+            check(format!("Project\n  Daily <!-- {daily_id} -->")),
+            Err(OutlineError::UnknownItem(daily_id.clone()))
+        );
+        assert_eq!(
+            check(format!(
+                "Project\n  Step <!-- {step_id} -->\n  Step <!-- {step_id} -->"
+            )),
+            Err(OutlineError::DuplicateChild("Step".to_string()))
+        );
+        assert_eq!(
+            check(format!(
+                "Project\n  Step <!-- {step_id} -->\n  Other\n    Step <!-- {step_id} -->\n      New"
+            )),
+            Err(OutlineError::RepeatedWithChildren("Step".to_string()))
+        );
+        assert_eq!(check("Project\n  Step\n".to_string()), Ok(()));
+    }
+
+    #[test]
+    fn custom_states_round_trip() {
+        let (_, content) = FORMAT_FIXTURES[6];
+        let list = List::try_from_str(content).unwrap();
+
+        assert_eq!(
+            list.get_list_item("groceries").unwrap().state,
+            State::Custom("InProgress".to_string())
+        );
+        assert_eq!(list.get_list_item("milk").unwrap().state, State::Completed);
+
+        let saved: serde_json::Value = serde_json::from_str(&list.into_string()).unwrap();
+        assert_eq!(saved["list_item_store"]["chores"]["state"], "Waiting");
+        assert_eq!(saved["list_item_store"]["milk"]["state"], "Completed");
+        assert_eq!(State::from("Blocked"), State::Blocked);
+    }
+
+    #[test]
+    fn workflow_cycles_through_declared_states() {
+        let workflow = Workflow::new(vec![
+            StateDefinition::new(State::Pending, "☐", "gray"),
+            StateDefinition::new(State::from("InProgress"), "◐", "yellow"),
+            StateDefinition {
+                done: true,
+                ..StateDefinition::new(State::from("Cancelled"), "✘", "darkgray")
+            },
+        ])
+        .unwrap();
+
+        assert_eq!(workflow.next(&State::Pending), State::from("InProgress"));
+        assert_eq!(workflow.next(&State::from("Cancelled")), State::Pending);
+        // States the workflow does not know restart the cycle.
+        assert_eq!(workflow.next(&State::Blocked), State::Pending);
+
+        assert!(workflow.is_done(&State::from("Cancelled")));
+        assert!(!workflow.is_done(&State::Completed));
+
+        assert_eq!(Workflow::new(Vec::new()), Err(WorkflowError::NoStates));
+        assert_eq!(
+            Workflow::new(vec![
+                StateDefinition::new(State::Pending, "☐", "gray"),
+                StateDefinition::new(State::from("Pending"), "○", "white"),
+            ]),
+            Err(WorkflowError::DuplicateState("Pending".to_string()))
+        );
+    }
+
+    #[test]
+    fn can_parse_relative_dates() {
+        // A Wednesday.
+        let today = NaiveDate::from_ymd_opt(2025, 3, 5).unwrap();
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d);
+
+        assert_eq!(parse_date("2025-12-24", today), date(2025, 12, 24));
+        assert_eq!(parse_date(" Today ", today), Some(today));
+        assert_eq!(parse_date("tomorrow", today), date(2025, 3, 6));
+        assert_eq!(parse_date("yesterday", today), date(2025, 3, 4));
+        assert_eq!(parse_date("fri", today), date(2025, 3, 7));
+        assert_eq!(parse_date("wednesday", today), date(2025, 3, 12));
+        assert_eq!(parse_date("+3", today), date(2025, 3, 8));
+        assert_eq!(parse_date("+2w", today), date(2025, 3, 19));
+        assert_eq!(parse_date("+1m", today), date(2025, 4, 5));
+        assert_eq!(parse_date("soon", today), None);
+        assert_eq!(parse_date("+3y", today), None);
+        assert_eq!(parse_date("2025-02-30", today), None);
+    }
+
+    #[test]
+    fn can_extract_tags_from_text() {
+        let (text, tags) = extract_tags("Buy #errand milk #Dairy and fix #42 #errand #");
+
+        assert_eq!(text, "Buy milk and fix #42 #");
+        assert_eq!(tags, vec!["errand", "Dairy"]);
+
+        let (text, tags) = extract_tags("a #x\nb");
+        assert_eq!(text, "a\nb");
+        assert_eq!(tags, vec!["x"]);
+
+        // Only the tags and the spaces before them go.
+        let (text, _) = extract_tags("#x First  line #y\n\n  - indented #z\n#x #w\nlast ");
+        assert_eq!(text, "First  line\n\n  - indented\nlast ");
+        assert_eq!(extract_tags("no  tags\n here ").0, "no  tags\n here ");
+
+        assert_eq!(
+            parse_tag_list("#work, home  #Work q2/plans bad!tag"),
+            vec!["work", "home", "q2/plans"]
+        );
+    }
+
+    #[test]
+    fn tags_are_listed_once_ignoring_case() {
+        let (_, content) = FORMAT_FIXTURES[4];
+        let mut list = List::try_from_str(content).unwrap();
+
+        assert!(list.get_list_item("milk").unwrap().has_tag("DAIRY"));
+        assert!(!list.get_list_item("bread").unwrap().has_tag("errand"));
+
+        list.get_mut_list_item("bread").unwrap().tags = vec!["Errand".to_string()];
+
+        assert_eq!(list.all_tags(), vec!["dairy", "errand"]);
+
+        list.get_mut_list_item("chores").unwrap().tags = vec!["Épicerie".to_string()];
+        list.get_mut_list_item("bread").unwrap().tags = vec!["épicerie".to_string()];
+
+        assert!(list.get_list_item("chores").unwrap().has_tag("ÉPICERIE"));
+        assert_eq!(list.all_tags(), vec!["dairy", "errand", "épicerie"]);
+        assert_eq!(extract_tags("Deal with #Ärger #ÄRGER").1, vec!["Ärger"]);
+    }
+
+    #[test]
+    fn priority_steps_stop_at_both_ends() {
+        assert_eq!(Priority::None.lowered(), Priority::None);
+        assert_eq!(Priority::None.raised(), Priority::Low);
+        assert_eq!(Priority::High.raised(), Priority::Urgent);
+        assert_eq!(Priority::Urgent.raised(), Priority::Urgent);
+        assert_eq!(Priority::Urgent.lowered(), Priority::High);
+        assert!(Priority::Urgent > Priority::Medium);
+    }
+
     #[test]
     fn can_remove_list_items() {
         let mut list = super::get_sample_list();
@@ -1,29 +1,104 @@
+use crate::archive::ArchivedItem;
 use crate::list_item::ListItem;
+use crate::migration::{self, CURRENT_FORMAT_VERSION};
+use crate::tags::same_tag;
+use crate::workflow::Workflow;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct List {
-    list_item_store: HashMap<String, ListItem>,
-    top_level_items: Vec<String>,
+    pub(crate) list_item_store: HashMap<String, ListItem>,
+    pub(crate) top_level_items: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) archive: Vec<ArchivedItem>,
+}
+
+/// How many of an item's descendants are done.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl Default for List {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl List {
     pub fn new() -> List {
-        let list = List {
+        List {
             list_item_store: HashMap::new(),
             top_level_items: Vec::new(),
-        };
+            archive: Vec::new(),
+        }
+    }
+
+    /// Parses a serialized list, checking that every id it references exists
+    /// and that no item is its own descendant.
+    ///
+    /// An empty (or whitespace only) string is treated as an empty list so a
+    /// freshly created file can be opened.
+    pub fn try_from_str(serialized_string: &str) -> Result<List, LoadError> {
+        let list = List::try_from_str_unchecked(serialized_string)?;
+
+        list.check_references()?;
 
-        list
+        Ok(list)
     }
 
+    /// Parses a serialized list, panicking if it is damaged.
+    #[deprecated(
+        note = "use `List::try_from_str`, which reports damaged lists instead of panicking"
+    )]
     pub fn from_string(serialized_string: String) -> List {
-        if serialized_string.is_empty() {
-            return List::new();
+        List::try_from_str(&serialized_string).unwrap()
+    }
+
+    /// Parses a serialized list without checking its references, so a damaged
+    /// list can still be inspected with [`List::validate`] and fixed with
+    /// [`List::repair`].
+    ///
+    /// Lists written in an older format are migrated to the current one.
+    pub fn try_from_str_unchecked(serialized_string: &str) -> Result<List, LoadError> {
+        if serialized_string.trim().is_empty() {
+            return Ok(List::new());
+        }
+
+        let value = serde_json::from_str(serialized_string).map_err(LoadError::from)?;
+
+        serde_json::from_value(migration::migrate(value)?).map_err(LoadError::from)
+    }
+
+    fn check_references(&self) -> Result<(), LoadError> {
+        for id in self.top_level_items.iter() {
+            if !self.list_item_store.contains_key(id) {
+                return Err(LoadError::MissingTopLevelId(id.clone()));
+            }
+        }
+
+        for item in self.list_item_store.values() {
+            for child_id in item.children.iter() {
+                if !self.list_item_store.contains_key(child_id) {
+                    return Err(LoadError::DanglingChildId {
+                        parent_id: item.id.clone(),
+                        child_id: child_id.clone(),
+                    });
+                }
+            }
         }
 
-        serde_json::from_str(&serialized_string).unwrap()
+        if let Some((parent_id, child_id)) = self.find_cycle_edges().into_iter().next() {
+            return Err(LoadError::Cycle {
+                parent_id,
+                child_id,
+            });
+        }
+
+        Ok(())
     }
 
     pub fn add_list_item(&mut self, item: ListItem, index: Option<usize>) {
@@ -39,10 +114,18 @@ impl List {
 
     pub fn add_existing_child_list_item(
         &mut self,
-        item_id: &String,
-        parent_id: &String,
+        item_id: &str,
+        parent_id: &str,
         index: Option<usize>,
     ) -> Result<(), ListItemTaskError> {
+        if !self.list_item_store.contains_key(item_id) {
+            return Err(ListItemTaskError::ChildIdDoesNotExist);
+        }
+
+        if self.is_ancestor_or_self(item_id, parent_id) {
+            return Err(ListItemTaskError::WouldCreateCycle);
+        }
+
         let parent = match self.list_item_store.get_mut(parent_id) {
             Some(parent) => parent,
             None => {
@@ -50,13 +133,13 @@ impl List {
             }
         };
 
-        if !parent.children.contains(&item_id) {
+        if !parent.children.iter().any(|c| c == item_id) {
             let insertion_index = match index {
                 Some(index) => index,
                 None => parent.children.len(),
             };
 
-            parent.children.insert(insertion_index, item_id.clone());
+            parent.children.insert(insertion_index, item_id.to_string());
         } else {
             return Err(ListItemTaskError::ParentAlreadyHasItem);
         }
@@ -67,7 +150,7 @@ impl List {
     pub fn add_child_list_item(
         &mut self,
         item: ListItem,
-        parent_id: &String,
+        parent_id: &str,
         index: Option<usize>,
     ) -> Result<(), ListItemTaskError> {
         let mut item_does_not_exist: bool = false;
@@ -86,20 +169,87 @@ impl List {
         self.add_existing_child_list_item(&item_id, parent_id, index)
     }
 
+    /// Moves a single occurrence of the item from under `from_parent_id` (or
+    /// the top level) to `index` under `to_parent_id` (or the top level).
+    /// `index` counts the new siblings without the item and is clamped to
+    /// their number.
+    pub fn move_item(
+        &mut self,
+        item_id: &str,
+        from_parent_id: Option<&str>,
+        to_parent_id: Option<&str>,
+        index: usize,
+    ) -> Result<(), ListItemTaskError> {
+        let from_index = self.get_index_in(item_id, from_parent_id)?;
+
+        if from_parent_id != to_parent_id {
+            if let Some(to_parent_id) = to_parent_id
+                && self.is_ancestor_or_self(item_id, to_parent_id)
+            {
+                return Err(ListItemTaskError::WouldCreateCycle);
+            }
+
+            if self.get_index_in(item_id, to_parent_id).is_ok() {
+                return Err(ListItemTaskError::ParentAlreadyHasItem);
+            }
+        }
+
+        // Check the destination before changing anything.
+        self.siblings_mut(to_parent_id)?;
+
+        let moved_id = self.siblings_mut(from_parent_id)?.remove(from_index);
+        let siblings = self.siblings_mut(to_parent_id)?;
+        siblings.insert(index.min(siblings.len()), moved_id);
+
+        Ok(())
+    }
+
+    /// The position of the item under `parent_id`, or at the top level.
+    pub fn get_index_in(
+        &self,
+        item_id: &str,
+        parent_id: Option<&str>,
+    ) -> Result<usize, ListItemTaskError> {
+        match parent_id {
+            Some(parent_id) => self.get_index_of_child(item_id, parent_id),
+            None => self.get_index_of_top_level_item(item_id),
+        }
+    }
+
+    fn siblings_mut(
+        &mut self,
+        parent_id: Option<&str>,
+    ) -> Result<&mut Vec<String>, ListItemTaskError> {
+        match parent_id {
+            Some(parent_id) => match self.list_item_store.get_mut(parent_id) {
+                Some(parent) => Ok(&mut parent.children),
+                None => Err(ListItemTaskError::ParentIdDoesNotExist),
+            },
+            None => Ok(&mut self.top_level_items),
+        }
+    }
+
     pub fn get_top_level_list_items(&self) -> Vec<&ListItem> {
         self.top_level_items
             .iter()
-            .map(|i| self.list_item_store.get(i).unwrap())
+            .filter_map(|i| self.list_item_store.get(i))
             .collect()
     }
 
     pub fn get_children(&self, item: &ListItem) -> Vec<&ListItem> {
         item.children
             .iter()
-            .map(|i| self.list_item_store.get(i).unwrap())
+            .filter_map(|i| self.list_item_store.get(i))
             .collect()
     }
 
+    pub(crate) fn children_count(&self, parent_id: &str) -> Result<usize, ListItemTaskError> {
+        match self.list_item_store.get(parent_id) {
+            Some(parent) => Ok(parent.children.len()),
+            None => Err(ListItemTaskError::ParentIdDoesNotExist),
+        }
+    }
+
     pub fn get_list_item(&self, id: &str) -> Option<&ListItem> {
         self.list_item_store.get(id)
     }
@@ -108,56 +258,310 @@ impl List {
         self.list_item_store.get_mut(id)
     }
 
-    pub fn remove_list_item(&mut self, id: &str) -> Result<(), ListItemDeletionError> {
-        if self.list_item_store.contains_key(id) {
-            self.list_item_store.remove(id);
+    /// How many of the item's descendants are done, counting items reachable
+    /// through several shared references once. `None` if it has no children.
+    pub fn progress(&self, id: &str, workflow: &Workflow) -> Option<Progress> {
+        let descendants = self.get_descendants(id);
+
+        (!descendants.is_empty()).then(|| Progress {
+            done: descendants
+                .iter()
+                .filter(|descendant| workflow.is_done(&descendant.state))
+                .count(),
+            total: descendants.len(),
+        })
+    }
+
+    /// Everything below the item, each item once however often it is
+    /// referenced.
+    pub fn get_descendants(&self, id: &str) -> Vec<&ListItem> {
+        let mut descendants = Vec::new();
+        let mut visited = HashSet::from([id]);
+        let mut pending: Vec<&ListItem> = match self.list_item_store.get(id) {
+            Some(item) => self.get_children(item),
+            None => Vec::new(),
+        };
+        pending.reverse();
+
+        while let Some(descendant) = pending.pop() {
+            if !visited.insert(&descendant.id) {
+                continue;
+            }
+
+            descendants.push(descendant);
+            pending.extend(self.get_children(descendant).into_iter().rev());
+        }
+
+        descendants
+    }
+
+    /// Copies of the item and everything below it with fresh ids, the copy of
+    /// the item first. Items shared within the subtree stay shared between
+    /// the copies. The copies are not added to the list.
+    pub fn deep_clone_subtree(&self, id: &str) -> Option<Vec<ListItem>> {
+        let item = self.list_item_store.get(id)?;
+        let originals: Vec<&ListItem> = std::iter::once(item)
+            .chain(self.get_descendants(id))
+            .collect();
+
+        let new_ids: HashMap<&str, String> = originals
+            .iter()
+            .map(|original| (original.id.as_str(), ListItem::new_id()))
+            .collect();
+
+        Some(
+            originals
+                .into_iter()
+                .map(|original| {
+                    let mut copy = original.clone();
+                    copy.id = new_ids[original.id.as_str()].clone();
+                    copy.children = original
+                        .children
+                        .iter()
+                        .filter_map(|child_id| new_ids.get(child_id.as_str()).cloned())
+                        .collect();
+                    copy
+                })
+                .collect(),
+        )
+    }
+
+    /// Items moved out of the list, oldest first.
+    pub fn archived_items(&self) -> &[ArchivedItem] {
+        &self.archive
+    }
+
+    /// Whether the item has children and every one of them is done.
+    pub fn children_done(&self, id: &str, workflow: &Workflow) -> bool {
+        self.list_item_store.get(id).is_some_and(|item| {
+            !item.children.is_empty()
+                && self
+                    .get_children(item)
+                    .iter()
+                    .all(|child| workflow.is_done(&child.state))
+        })
+    }
+
+    /// Every tag used in the list, sorted and without duplicates (ignoring
+    /// case).
+    pub fn all_tags(&self) -> Vec<&str> {
+        let mut tags: Vec<&str> = self
+            .list_item_store
+            .values()
+            .flat_map(|item| item.tags.iter().map(|t| t.as_str()))
+            .collect();
+
+        // Lowercase spellings first, so they are the ones kept.
+        tags.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()).then(b.cmp(a)));
+        tags.dedup_by(|a, b| same_tag(a, b));
+        tags
+    }
+
+    /// Ids of every item that lists `id` as one of its children.
+    pub fn get_parent_ids(&self, id: &str) -> Vec<&str> {
+        let mut parent_ids: Vec<&str> = self
+            .list_item_store
+            .values()
+            .filter(|item| item.children.iter().any(|c| c == id))
+            .map(|item| item.id.as_str())
+            .collect();
+
+        parent_ids.sort();
+        parent_ids
+    }
+
+    /// Walks up from `id` through every parent, returning true if
+    /// `ancestor_id` is `id` itself or one of its ancestors.
+    pub fn is_ancestor_or_self(&self, ancestor_id: &str, id: &str) -> bool {
+        let mut visited = HashSet::new();
+        let mut pending = vec![id];
+
+        while let Some(current_id) = pending.pop() {
+            if current_id == ancestor_id {
+                return true;
+            }
+
+            if visited.insert(current_id) {
+                pending.extend(self.get_parent_ids(current_id));
+            }
+        }
+
+        false
+    }
+
+    /// Every place the item appears in, as paths of ids from a top level
+    /// item down to it. Places that can't be reached from the top level are
+    /// left out.
+    pub fn get_locations(&self, id: &str) -> Vec<Vec<String>> {
+        let mut locations = Vec::new();
+        self.collect_locations(id, &mut Vec::new(), &mut locations);
+
+        for location in locations.iter_mut() {
+            location.reverse();
+        }
+
+        locations.sort();
+        locations
+    }
+
+    /// Walks up from `id`, with `below` holding the ids already walked
+    /// through, nearest first.
+    fn collect_locations(
+        &self,
+        id: &str,
+        below: &mut Vec<String>,
+        locations: &mut Vec<Vec<String>>,
+    ) {
+        if below.iter().any(|b| b == id) {
+            return;
+        }
+
+        below.push(id.to_string());
+
+        if self.top_level_items.iter().any(|i| i == id) {
+            locations.push(below.clone());
+        }
+
+        for parent_id in self.get_parent_ids(id) {
+            self.collect_locations(parent_id, below, locations);
+        }
+
+        below.pop();
+    }
+
+    /// How many places every item appears in, for items that appear
+    /// somewhere. Cheaper than asking for each item with
+    /// [`List::reference_count`].
+    pub fn reference_counts(&self) -> HashMap<&str, usize> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+
+        for id in self.top_level_items.iter().chain(
+            self.list_item_store
+                .values()
+                .flat_map(|item| item.children.iter()),
+        ) {
+            *counts.entry(id.as_str()).or_default() += 1;
+        }
+
+        counts
+    }
+
+    /// Number of places the item appears in, counting the top level as one.
+    pub fn reference_count(&self, id: &str) -> usize {
+        let top_level_count = if self.top_level_items.iter().any(|i| i == id) {
+            1
         } else {
+            0
+        };
+
+        self.get_parent_ids(id).len() + top_level_count
+    }
+
+    /// Deletes the item everywhere it appears, along with any of its
+    /// descendants that are not referenced from somewhere else.
+    ///
+    /// Returns every item that was removed from the list.
+    pub fn remove_list_item(&mut self, id: &str) -> Result<Vec<ListItem>, ListItemDeletionError> {
+        if !self.list_item_store.contains_key(id) {
             return Err(ListItemDeletionError::ItemNotExist);
         }
 
-        for item in self.list_item_store.iter_mut().by_ref() {
-            item.1.children.retain(|c| c != id);
+        let candidates = self.get_subtree_ids(id);
+
+        self.top_level_items.retain(|i| i != id);
+
+        for item in self.list_item_store.values_mut() {
+            item.children.retain(|c| c != id);
         }
 
-        Ok(())
+        Ok(self.collect_garbage(candidates))
     }
 
+    /// Unlinks the item from a single parent (or from the top level when
+    /// `parent_id` is `None`). Other references to the item are left alone;
+    /// if this was the last one, the item and any descendants that are no
+    /// longer reachable are deleted.
+    ///
+    /// Returns every item that was removed from the list.
     pub fn remove_child_list_item(
         &mut self,
-        child_id: &String,
-        parent_id: Option<&String>,
-    ) -> Result<(), ListItemDeletionError> {
+        child_id: &str,
+        parent_id: Option<&str>,
+    ) -> Result<Vec<ListItem>, ListItemDeletionError> {
         if !self.list_item_store.contains_key(child_id) {
             return Err(ListItemDeletionError::ChildDoesNotExist);
         }
 
-        if let Some(parent_id) = parent_id {
-            if !self.list_item_store.contains_key(parent_id) {
-                return Err(ListItemDeletionError::ParentDoesNotExist);
-            }
-
-            self.get_mut_list_item(&parent_id)
-                .unwrap()
-                .children
-                .retain(|c| c != child_id);
+        let siblings = match parent_id {
+            Some(parent_id) => match self.list_item_store.get_mut(parent_id) {
+                Some(parent) => &mut parent.children,
+                None => return Err(ListItemDeletionError::ParentDoesNotExist),
+            },
+            None => &mut self.top_level_items,
+        };
 
-            Ok(())
-        } else {
-            self.top_level_items.retain(|i| i != child_id);
-            Ok(())
+        if !siblings.iter().any(|c| c == child_id) {
+            return Err(ListItemDeletionError::ChildNotInParent);
         }
+
+        siblings.retain(|c| c != child_id);
+
+        let candidates = self.get_subtree_ids(child_id);
+
+        Ok(self.collect_garbage(candidates))
+    }
+
+    pub(crate) fn get_subtree_ids(&self, id: &str) -> HashSet<String> {
+        self.reachable_ids(std::iter::once(id))
+            .into_iter()
+            .map(|i| i.to_string())
+            .collect()
+    }
+
+    /// Deletes the `candidates` that are no longer reachable from the top
+    /// level or from an item outside of the candidates.
+    pub(crate) fn collect_garbage(&mut self, candidates: HashSet<String>) -> Vec<ListItem> {
+        let roots: Vec<&str> = self
+            .top_level_items
+            .iter()
+            .map(|i| i.as_str())
+            .chain(
+                self.list_item_store
+                    .values()
+                    .filter(|item| !candidates.contains(&item.id))
+                    .flat_map(|item| item.children.iter().map(|c| c.as_str())),
+            )
+            .collect();
+
+        let kept: HashSet<String> = self
+            .reachable_ids(roots.into_iter())
+            .into_iter()
+            .map(|i| i.to_string())
+            .collect();
+
+        let mut garbage_ids: Vec<&String> = candidates.difference(&kept).collect();
+        garbage_ids.sort();
+
+        garbage_ids
+            .into_iter()
+            .filter_map(|id| self.list_item_store.remove(id))
+            .collect()
     }
 
     pub fn into_string(&self) -> String {
-        serde_json::to_string(&self).unwrap()
+        serde_json::to_string(&VersionedList {
+            format_version: CURRENT_FORMAT_VERSION,
+            list: self,
+        })
+        .unwrap()
     }
 
     pub fn get_index_of_child(
         &self,
-        child_id: &String,
-        parent_id: &String,
+        child_id: &str,
+        parent_id: &str,
     ) -> Result<usize, ListItemTaskError> {
-        if let Some(parent) = self.get_list_item(&parent_id) {
+        if let Some(parent) = self.get_list_item(parent_id) {
             for c in parent.children.iter().enumerate() {
                 if c.1 == child_id {
                     return Ok(c.0);
@@ -170,24 +574,55 @@ impl List {
         }
     }
 
-    pub fn get_index_of_top_level_item(
-        &self,
-        child_id: &String,
-    ) -> Result<usize, ListItemTaskError> {
+    pub fn get_index_of_top_level_item(&self, child_id: &str) -> Result<usize, ListItemTaskError> {
         for c in self.top_level_items.iter().enumerate() {
             if c.1 == child_id {
                 return Ok(c.0);
             }
         }
 
-        return Err(ListItemTaskError::ChildIdDoesNotExist);
+        Err(ListItemTaskError::ChildIdDoesNotExist)
+    }
+}
+
+/// The on-disk form of a [`List`], tagged with the format it was written in.
+#[derive(Serialize)]
+struct VersionedList<'a> {
+    format_version: u64,
+    #[serde(flatten)]
+    list: &'a List,
+}
+
+impl TryFrom<&str> for List {
+    type Error = LoadError;
+
+    fn try_from(serialized_string: &str) -> Result<Self, Self::Error> {
+        List::try_from_str(serialized_string)
     }
 }
 
+#[derive(Debug)]
 pub enum ListItemTaskError {
     ParentAlreadyHasItem,
     ParentIdDoesNotExist,
     ChildIdDoesNotExist,
+    WouldCreateCycle,
+}
+
+impl fmt::Display for ListItemTaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListItemTaskError::ParentAlreadyHasItem => {
+                write!(f, "The item is already a child of this parent")
+            }
+            ListItemTaskError::ParentIdDoesNotExist => write!(f, "The parent item does not exist"),
+            ListItemTaskError::ChildIdDoesNotExist => write!(f, "The item does not exist"),
+            ListItemTaskError::WouldCreateCycle => write!(
+                f,
+                "An item can not be placed inside itself or one of its descendants"
+            ),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -195,4 +630,85 @@ pub enum ListItemDeletionError {
     ItemNotExist,
     ChildDoesNotExist,
     ParentDoesNotExist,
+    ChildNotInParent,
 }
+
+impl fmt::Display for ListItemDeletionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListItemDeletionError::ItemNotExist | ListItemDeletionError::ChildDoesNotExist => {
+                write!(f, "The item does not exist")
+            }
+            ListItemDeletionError::ParentDoesNotExist => {
+                write!(f, "The parent item does not exist")
+            }
+            ListItemDeletionError::ChildNotInParent => {
+                write!(f, "The item is not a child of this parent")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    MalformedJson {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    DanglingChildId {
+        parent_id: String,
+        child_id: String,
+    },
+    MissingTopLevelId(String),
+    /// Following the children of `parent_id` into `child_id` leads back to
+    /// `parent_id`.
+    Cycle {
+        parent_id: String,
+        child_id: String,
+    },
+    /// The list was written by a newer version of `jl`, in this format version.
+    UnsupportedVersion(u64),
+}
+
+impl From<serde_json::Error> for LoadError {
+    fn from(error: serde_json::Error) -> Self {
+        LoadError::MalformedJson {
+            line: error.line(),
+            column: error.column(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::MalformedJson { message, .. } => write!(f, "Malformed JSON: {message}"),
+            LoadError::DanglingChildId {
+                parent_id,
+                child_id,
+            } => write!(
+                f,
+                "Item `{parent_id}` refers to child `{child_id}` which does not exist"
+            ),
+            LoadError::MissingTopLevelId(id) => {
+                write!(f, "Top level item `{id}` does not exist")
+            }
+            LoadError::Cycle {
+                parent_id,
+                child_id,
+            } => write!(
+                f,
+                "Item `{parent_id}` has child `{child_id}` which is also one of its ancestors"
+            ),
+            LoadError::UnsupportedVersion(version) => write!(
+                f,
+                "The list was saved by a newer version of jl (format version {version}, \
+                this version reads up to {CURRENT_FORMAT_VERSION})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
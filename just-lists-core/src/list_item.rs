@@ -1,38 +1,156 @@
+use crate::tags::same_tag;
+use crate::workflow::Workflow;
+use chrono::NaiveDate;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ListItem {
     pub id: String,
     pub value: String,
     pub(crate) children: Vec<String>,
-    #[serde(default = "State::default")]
     pub state: State,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<NaiveDate>,
+    /// The day work on the item is planned to start.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Priority::is_none")]
+    pub priority: Priority,
+    /// Longer text about the item, written in Markdown.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Where an item is in its workflow. Besides the built in states, users can
+/// declare their own in a [`Workflow`](crate::workflow::Workflow); those are
+/// stored by name.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
 pub enum State {
     Pending,
     Completed,
     Blocked,
+    Custom(String),
 }
 
 impl State {
-    fn default() -> Self {
-        State::Pending
+    pub fn name(&self) -> &str {
+        match self {
+            State::Pending => "Pending",
+            State::Completed => "Completed",
+            State::Blocked => "Blocked",
+            State::Custom(name) => name,
+        }
+    }
+}
+
+impl From<&str> for State {
+    fn from(name: &str) -> State {
+        match name {
+            "Pending" => State::Pending,
+            "Completed" => State::Completed,
+            "Blocked" => State::Blocked,
+            _ => State::Custom(name.to_string()),
+        }
+    }
+}
+
+impl From<String> for State {
+    fn from(name: String) -> State {
+        State::from(name.as_str())
+    }
+}
+
+impl From<State> for String {
+    fn from(state: State) -> String {
+        match state {
+            State::Custom(name) => name,
+            state => state.name().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How important an item is, from lowest to highest.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    pub fn is_none(&self) -> bool {
+        *self == Priority::None
+    }
+
+    /// The next higher priority, staying at [`Priority::Urgent`].
+    pub fn raised(self) -> Priority {
+        match self {
+            Priority::None => Priority::Low,
+            Priority::Low => Priority::Medium,
+            Priority::Medium => Priority::High,
+            Priority::High | Priority::Urgent => Priority::Urgent,
+        }
+    }
+
+    /// The next lower priority, staying at [`Priority::None`].
+    pub fn lowered(self) -> Priority {
+        match self {
+            Priority::None | Priority::Low => Priority::None,
+            Priority::Medium => Priority::Low,
+            Priority::High => Priority::Medium,
+            Priority::Urgent => Priority::High,
+        }
     }
 }
 
 impl ListItem {
     pub fn new(value: String) -> ListItem {
         ListItem {
-            id: Self::random_string_from_chars(32),
-            value: value,
+            id: Self::new_id(),
+            value,
             children: Vec::<String>::new(),
             state: State::Pending,
+            due: None,
+            scheduled: None,
+            tags: Vec::new(),
+            priority: Priority::None,
+            notes: None,
         }
     }
 
+    /// Whether the item carries `tag`, ignoring case.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| same_tag(t, tag))
+    }
+
+    /// Whether the item is still open and its due date has passed.
+    pub fn is_overdue(&self, today: NaiveDate, workflow: &Workflow) -> bool {
+        !workflow.is_done(&self.state) && self.due.is_some_and(|due| due < today)
+    }
+
+    /// Whether the item is still open and due `today`.
+    pub fn is_due_today(&self, today: NaiveDate, workflow: &Workflow) -> bool {
+        !workflow.is_done(&self.state) && self.due == Some(today)
+    }
+
+    pub(crate) fn new_id() -> String {
+        Self::random_string_from_chars(32)
+    }
+
     fn random_string_from_chars(length: usize) -> String {
         const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
 
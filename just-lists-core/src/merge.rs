@@ -0,0 +1,202 @@
+use crate::archive::ArchivedItem;
+use crate::list::List;
+use crate::list_item::ListItem;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// The outcome of [`merge`].
+#[derive(Debug)]
+pub struct MergeResult {
+    pub list: List,
+    /// Fields that were changed differently on both sides. The value from
+    /// `ours` was kept for each of them.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MergeConflict {
+    pub item_id: String,
+    pub field: String,
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` of item `{}` was changed on both sides",
+            self.field, self.item_id
+        )
+    }
+}
+
+/// Three-way merge of two lists that were both edited from `base`, matching
+/// items by id.
+///
+/// - Fields of an item are merged one by one; when both sides changed the
+///   same field differently the value from `ours` wins and a conflict is
+///   reported.
+/// - Children and top level items are merged as ordered sets: additions and
+///   removals from both sides are kept.
+/// - An item deleted on one side is kept if the other side changed it.
+///
+/// The merged list is repaired afterwards, so it never contains dangling
+/// references or cycles.
+pub fn merge(base: &List, ours: &List, theirs: &List) -> MergeResult {
+    let mut list = List::new();
+    let mut conflicts = Vec::new();
+
+    let ids: BTreeSet<&String> = base
+        .list_item_store
+        .keys()
+        .chain(ours.list_item_store.keys())
+        .chain(theirs.list_item_store.keys())
+        .collect();
+
+    for id in ids {
+        let base_item = base.get_list_item(id);
+
+        let merged_item = match (ours.get_list_item(id), theirs.get_list_item(id)) {
+            (Some(our_item), Some(their_item)) => {
+                Some(merge_item(base_item, our_item, their_item, &mut conflicts))
+            }
+            (Some(item), None) | (None, Some(item)) => keep_unless_deleted(base_item, item),
+            (None, None) => None,
+        };
+
+        if let Some(item) = merged_item {
+            list.list_item_store.insert(id.clone(), item);
+        }
+    }
+
+    list.top_level_items = merge_sequence(
+        &base.top_level_items,
+        &ours.top_level_items,
+        &theirs.top_level_items,
+    );
+
+    list.archive = merge_archive(&base.archive, &ours.archive, &theirs.archive);
+
+    list.repair();
+
+    MergeResult { list, conflicts }
+}
+
+/// An item that only one side still has is either new on that side, or was
+/// deleted by the other side. A deletion wins unless the item was changed.
+fn keep_unless_deleted(base_item: Option<&ListItem>, item: &ListItem) -> Option<ListItem> {
+    match base_item {
+        Some(base_item) if base_item == item => None,
+        _ => Some(item.clone()),
+    }
+}
+
+fn merge_item(
+    base_item: Option<&ListItem>,
+    our_item: &ListItem,
+    their_item: &ListItem,
+    conflicts: &mut Vec<MergeConflict>,
+) -> ListItem {
+    let base_fields = base_item.map(item_fields).unwrap_or_default();
+    let our_fields = item_fields(our_item);
+    let their_fields = item_fields(their_item);
+
+    let mut merged_fields = our_fields.clone();
+
+    let keys: BTreeSet<&String> = our_fields.keys().chain(their_fields.keys()).collect();
+
+    for key in keys {
+        if key == "children" {
+            continue;
+        }
+
+        let base_value = base_fields.get(key);
+        let our_value = our_fields.get(key);
+        let their_value = their_fields.get(key);
+
+        if our_value == their_value || their_value == base_value {
+            continue;
+        }
+
+        if our_value == base_value {
+            match their_value {
+                Some(value) => merged_fields.insert(key.clone(), value.clone()),
+                None => merged_fields.remove(key),
+            };
+        } else {
+            conflicts.push(MergeConflict {
+                item_id: our_item.id.clone(),
+                field: key.clone(),
+            });
+        }
+    }
+
+    let mut item: ListItem =
+        serde_json::from_value(Value::Object(merged_fields)).unwrap_or_else(|_| our_item.clone());
+
+    item.children = merge_sequence(
+        base_item.map(|i| i.children.as_slice()).unwrap_or_default(),
+        &our_item.children,
+        &their_item.children,
+    );
+
+    item
+}
+
+fn item_fields(item: &ListItem) -> Map<String, Value> {
+    match serde_json::to_value(item) {
+        Ok(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    }
+}
+
+/// Entries restored by either side are dropped and entries archived by
+/// `theirs` are added after those of `ours`.
+fn merge_archive(
+    base: &[ArchivedItem],
+    ours: &[ArchivedItem],
+    theirs: &[ArchivedItem],
+) -> Vec<ArchivedItem> {
+    let mut merged: Vec<ArchivedItem> = ours
+        .iter()
+        .filter(|entry| !base.contains(entry) || theirs.contains(entry))
+        .cloned()
+        .collect();
+
+    merged.extend(
+        theirs
+            .iter()
+            .filter(|entry| !base.contains(entry) && !ours.contains(entry))
+            .cloned(),
+    );
+
+    merged
+}
+
+/// Merges two edited versions of an ordered list of ids. Ids removed by
+/// either side are dropped and ids added by `theirs` are placed after the
+/// closest id that precedes them there; everything else keeps the order of
+/// `ours`.
+fn merge_sequence(base: &[String], ours: &[String], theirs: &[String]) -> Vec<String> {
+    let mut merged: Vec<String> = ours
+        .iter()
+        .filter(|id| !base.contains(id) || theirs.contains(id))
+        .cloned()
+        .collect();
+
+    for (index, id) in theirs.iter().enumerate() {
+        if base.contains(id) || merged.contains(id) {
+            continue;
+        }
+
+        let position = theirs[..index]
+            .iter()
+            .rev()
+            .find_map(|preceding_id| merged.iter().position(|m| m == preceding_id))
+            .map_or(0, |position| position + 1);
+
+        merged.insert(position, id.clone());
+    }
+
+    merged
+}
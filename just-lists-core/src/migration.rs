@@ -0,0 +1,95 @@
+//! Upgrades list files written by older versions of `jl`.
+//!
+//! Every saved list carries a top level `format_version`; files from before
+//! it was introduced are version `0`. Loading runs the raw JSON through every
+//! migration from the file's version up to [`CURRENT_FORMAT_VERSION`] before
+//! it is deserialized, so the structs in this crate only ever need to
+//! understand the current format.
+//!
+//! Bump the version for every change an older build would mishandle: a
+//! field it would drop when saving the file, or a value it can't read. Older
+//! builds then refuse the file instead of losing data. To change the format,
+//! bump [`CURRENT_FORMAT_VERSION`], append a migration to [`MIGRATIONS`] and
+//! add a fixture written in the new version under `tests/fixtures`.
+
+use crate::list::LoadError;
+use serde_json::{Map, Value};
+
+pub const CURRENT_FORMAT_VERSION: u64 = 7;
+
+const FORMAT_VERSION_KEY: &str = "format_version";
+
+type Migration = fn(&mut Map<String, Value>);
+
+/// `MIGRATIONS[n]` upgrades a list from version `n` to version `n + 1`.
+const MIGRATIONS: [Migration; CURRENT_FORMAT_VERSION as usize] = [
+    add_missing_states,
+    no_changes,
+    no_changes,
+    no_changes,
+    no_changes,
+    no_changes,
+    no_changes,
+];
+
+/// Upgrades a serialized list to [`CURRENT_FORMAT_VERSION`].
+pub fn migrate(mut value: Value) -> Result<Value, LoadError> {
+    let Some(list) = value.as_object_mut() else {
+        return Err(LoadError::MalformedJson {
+            line: 0,
+            column: 0,
+            message: "Expected a JSON object".to_string(),
+        });
+    };
+
+    let version = match list.get(FORMAT_VERSION_KEY) {
+        None => 0,
+        Some(version) => version.as_u64().ok_or_else(|| LoadError::MalformedJson {
+            line: 0,
+            column: 0,
+            message: format!("`{FORMAT_VERSION_KEY}` must be a whole number, found {version}"),
+        })?,
+    };
+
+    if version > CURRENT_FORMAT_VERSION {
+        return Err(LoadError::UnsupportedVersion(version));
+    }
+
+    for migration in MIGRATIONS[version as usize..].iter() {
+        migration(list);
+    }
+
+    list.insert(
+        FORMAT_VERSION_KEY.to_string(),
+        CURRENT_FORMAT_VERSION.into(),
+    );
+
+    Ok(value)
+}
+
+fn items_mut(list: &mut Map<String, Value>) -> impl Iterator<Item = &mut Map<String, Value>> {
+    list.get_mut("list_item_store")
+        .and_then(Value::as_object_mut)
+        .into_iter()
+        .flat_map(|store| store.values_mut())
+        .filter_map(Value::as_object_mut)
+}
+
+/// For versions that only added optional fields or values, which older
+/// files simply lack.
+///
+/// - Version 2 added `due` and `scheduled` dates.
+/// - Version 3 added `tags`.
+/// - Version 4 added `priority`.
+/// - Version 5 allowed states other than `Pending`, `Completed` and
+///   `Blocked`.
+/// - Version 6 added the `archive`.
+/// - Version 7 added `notes`.
+fn no_changes(_list: &mut Map<String, Value>) {}
+
+/// Version 0 files written before items had a state are all pending.
+fn add_missing_states(list: &mut Map<String, Value>) {
+    for item in items_mut(list) {
+        item.entry("state").or_insert_with(|| "Pending".into());
+    }
+}
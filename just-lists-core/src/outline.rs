@@ -0,0 +1,228 @@
+use crate::list::List;
+use std::collections::HashSet;
+use std::fmt;
+
+/// An item and everything below it, written as indented text so it can be
+/// edited in a text editor. Every line is an item, indented under its parent
+/// and followed by an `<!-- id -->` marker; lines without a marker are new
+/// items. Line breaks within an item are written as `\n`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineItem {
+    /// `None` for items added in the outline.
+    pub id: Option<String>,
+    pub value: String,
+    pub children: Vec<OutlineItem>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OutlineError {
+    Empty,
+    /// A line after the first one is not indented below it.
+    SeveralRoots {
+        line: usize,
+    },
+    /// The first line is not the item the outline was written for.
+    NotTheOutlinedItem,
+    /// A marker names an item that was not in the outline.
+    UnknownItem(String),
+    /// Items appearing in several places list their children only once.
+    RepeatedWithChildren(String),
+    /// An item appears twice directly under the same parent.
+    DuplicateChild(String),
+}
+
+impl fmt::Display for OutlineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutlineError::Empty => write!(f, "The outline is empty"),
+            OutlineError::SeveralRoots { line } => write!(
+                f,
+                "Line {line} is not indented below the first line, which has to hold everything else"
+            ),
+            OutlineError::NotTheOutlinedItem => {
+                write!(f, "The first line has to stay the item that was opened")
+            }
+            OutlineError::UnknownItem(id) => {
+                write!(f, "`<!-- {id} -->` is not an item of the outline")
+            }
+            OutlineError::RepeatedWithChildren(value) => write!(
+                f,
+                "\"{value}\" appears more than once; only the first place can list its children"
+            ),
+            OutlineError::DuplicateChild(value) => {
+                write!(f, "\"{value}\" appears twice under the same item")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OutlineError {}
+
+const INDENT: &str = "  ";
+
+/// Writes the item and everything below it. An item shared in several places
+/// below the item lists its children only the first time it appears.
+pub fn write_outline(list: &List, id: &str) -> Option<String> {
+    let item = list.get_list_item(id)?;
+    let mut text = String::new();
+    let mut written = HashSet::new();
+    let mut pending = vec![(0, item)];
+
+    while let Some((depth, item)) = pending.pop() {
+        text.push_str(&INDENT.repeat(depth));
+        text.push_str(&escape(&item.value));
+        text.push_str(&format!(" <!-- {} -->\n", item.id));
+
+        if written.insert(&item.id) {
+            pending.extend(
+                list.get_children(item)
+                    .into_iter()
+                    .rev()
+                    .map(|child| (depth + 1, child)),
+            );
+        }
+    }
+
+    Some(text)
+}
+
+/// Reads an outline. Any consistent indentation works, and blank lines are
+/// ignored.
+pub fn parse_outline(text: &str) -> Result<OutlineItem, OutlineError> {
+    // Items whose children may still follow, with their indentation.
+    let mut open: Vec<(usize, OutlineItem)> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let content = line.trim_start();
+        let indent = line[..line.len() - content.len()]
+            .chars()
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum();
+        let item = parse_line(content);
+
+        if let Some((root_indent, _)) = open.first()
+            && indent <= *root_indent
+        {
+            return Err(OutlineError::SeveralRoots { line: index + 1 });
+        }
+
+        close_deeper(&mut open, indent);
+        open.push((indent, item));
+    }
+
+    close_deeper(&mut open, 0);
+    open.pop().map(|(_, root)| root).ok_or(OutlineError::Empty)
+}
+
+/// Adds every open item indented `indent` or deeper to its parent, leaving
+/// the root open.
+fn close_deeper(open: &mut Vec<(usize, OutlineItem)>, indent: usize) {
+    while open.len() > 1 && open.last().is_some_and(|(last, _)| *last >= indent) {
+        if let Some((_, item)) = open.pop()
+            && let Some((_, parent)) = open.last_mut()
+        {
+            parent.children.push(item);
+        }
+    }
+}
+
+fn parse_line(content: &str) -> OutlineItem {
+    let content = content.trim_end();
+
+    let marker = content
+        .strip_suffix("-->")
+        .and_then(|rest| rest.rsplit_once("<!--"));
+
+    match marker {
+        Some((value, id)) if !id.trim().is_empty() => OutlineItem {
+            id: Some(id.trim().to_string()),
+            value: unescape(value.trim_end()),
+            children: Vec::new(),
+        },
+        _ => OutlineItem {
+            id: None,
+            value: unescape(content),
+            children: Vec::new(),
+        },
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut value = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                value.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                value.push('\\');
+                chars.next();
+            }
+            (c, _) => value.push(c),
+        }
+    }
+
+    value
+}
+
+impl OutlineItem {
+    /// Checks that the outline can replace the item `id` and everything
+    /// below it: it has to start with that item and may only refer to items
+    /// that were below it.
+    pub fn check(&self, list: &List, id: &str) -> Result<(), OutlineError> {
+        if self.id.as_deref().is_some_and(|root_id| root_id != id) {
+            return Err(OutlineError::NotTheOutlinedItem);
+        }
+
+        let known: HashSet<&str> = list
+            .get_descendants(id)
+            .into_iter()
+            .map(|item| item.id.as_str())
+            .collect();
+        let mut seen = HashSet::from([id]);
+
+        self.check_children(&known, &mut seen)
+    }
+
+    fn check_children<'a>(
+        &'a self,
+        known: &HashSet<&str>,
+        seen: &mut HashSet<&'a str>,
+    ) -> Result<(), OutlineError> {
+        let mut siblings = HashSet::new();
+
+        for child in &self.children {
+            let Some(child_id) = child.id.as_deref() else {
+                child.check_children(known, seen)?;
+                continue;
+            };
+
+            if !known.contains(child_id) {
+                return Err(OutlineError::UnknownItem(child_id.to_string()));
+            }
+
+            if !siblings.insert(child_id) {
+                return Err(OutlineError::DuplicateChild(child.value.clone()));
+            }
+
+            if seen.insert(child_id) {
+                child.check_children(known, seen)?;
+            } else if !child.children.is_empty() {
+                return Err(OutlineError::RepeatedWithChildren(child.value.clone()));
+            }
+        }
+
+        Ok(())
+    }
+}
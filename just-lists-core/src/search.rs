@@ -0,0 +1,118 @@
+use crate::list::List;
+use crate::list_item::ListItem;
+use std::collections::HashSet;
+
+/// An item matching a search, and where it is in the list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    /// Ids from a top level item down to the match.
+    pub id_path: Vec<String>,
+    pub score: i64,
+    /// Whether the query was found in the item's notes rather than its value.
+    pub in_notes: bool,
+}
+
+/// Scores how well `query` fuzzy-matches `text`. Every character of the
+/// query has to appear in `text` in order, ignoring case; runs of
+/// consecutive characters and matches at the start of words score higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let mut query_chars = query.chars().flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut previous_matched = false;
+    let mut previous_char: Option<char> = None;
+
+    for c in text.chars() {
+        let Some(&wanted) = query_chars.peek() else {
+            break;
+        };
+
+        let matched = c.to_lowercase().eq(std::iter::once(wanted));
+
+        if matched {
+            query_chars.next();
+            score += 1;
+
+            if previous_matched {
+                score += 5;
+            }
+
+            if previous_char.is_none_or(|p| !p.is_alphanumeric()) {
+                score += 3;
+            }
+        }
+
+        previous_matched = matched;
+        previous_char = Some(c);
+    }
+
+    query_chars.peek().is_none().then_some(score)
+}
+
+/// Every item whose value matches `query`, best matches first, followed by
+/// the items whose notes contain it (ignoring case). Items shared in several
+/// places are reported once, at the first place they appear.
+pub fn search(list: &List, query: &str) -> Vec<SearchResult> {
+    if query.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let mut results = Vec::new();
+    let mut visited = HashSet::new();
+
+    for item in list.get_top_level_list_items() {
+        search_subtree(
+            list,
+            item,
+            query,
+            &mut Vec::new(),
+            &mut visited,
+            &mut results,
+        );
+    }
+
+    results.sort_by_key(|result: &SearchResult| -result.score);
+    results
+}
+
+fn search_subtree<'a>(
+    list: &'a List,
+    item: &'a ListItem,
+    query: &str,
+    id_path: &mut Vec<String>,
+    visited: &mut HashSet<&'a str>,
+    results: &mut Vec<SearchResult>,
+) {
+    if !visited.insert(&item.id) {
+        return;
+    }
+
+    id_path.push(item.id.clone());
+
+    // Fuzzy matching long notes would find almost anything, so the query
+    // has to appear in them as typed.
+    let in_notes = || {
+        item.notes
+            .as_deref()
+            .is_some_and(|notes| notes.to_lowercase().contains(&query.trim().to_lowercase()))
+    };
+
+    if let Some(score) = fuzzy_score(query, &item.value) {
+        results.push(SearchResult {
+            id_path: id_path.clone(),
+            score,
+            in_notes: false,
+        });
+    } else if in_notes() {
+        results.push(SearchResult {
+            id_path: id_path.clone(),
+            score: 0,
+            in_notes: true,
+        });
+    }
+
+    for child in list.get_children(item) {
+        search_subtree(list, child, query, id_path, visited, results);
+    }
+
+    id_path.pop();
+}
@@ -0,0 +1,442 @@
+use crate::history::History;
+use crate::list::{List, LoadError};
+use crate::merge::{self, MergeResult};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Reads and writes a [`List`] to a file on disk.
+///
+/// Saves never truncate the file in place: the new content is written to a
+/// temporary file next to it and renamed over the original, and the previous
+/// versions are kept as rotating `.bak` files. Backups are rotated on the
+/// first save of a session, and again when saving over changes made outside
+/// it, so they hold earlier sessions rather than the last few keystrokes.
+///
+/// Once the file has been loaded, saves are refused with
+/// [`StorageError::Conflict`] if something else changed it in the meantime.
+pub struct Storage {
+    path: PathBuf,
+    backup_count: usize,
+    loaded: Option<LoadedState>,
+    /// Whether the version of the file from before this session was backed
+    /// up already.
+    backed_up: bool,
+}
+
+/// What the file looked like the last time it was read or written.
+struct LoadedState {
+    /// `None` if the file did not exist.
+    stamp: Option<FileStamp>,
+    /// `None` if the file could not be parsed.
+    list: Option<List>,
+}
+
+#[derive(PartialEq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    hash: u64,
+}
+
+/// The undo history as kept next to the list, along with the hash of the
+/// list it was saved with.
+#[derive(Serialize, Deserialize)]
+struct SavedHistory<H> {
+    #[serde(default)]
+    list_hash: Option<u64>,
+    #[serde(flatten)]
+    history: H,
+}
+
+impl Storage {
+    pub const DEFAULT_BACKUP_COUNT: usize = 3;
+    const HISTORY_SUFFIX: &str = ".history.json";
+
+    pub fn new(path: impl Into<PathBuf>) -> Storage {
+        Storage {
+            path: path.into(),
+            backup_count: Self::DEFAULT_BACKUP_COUNT,
+            loaded: None,
+            backed_up: false,
+        }
+    }
+
+    pub fn with_backup_count(mut self, backup_count: usize) -> Storage {
+        self.backup_count = backup_count;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// A path next to the list file, made by appending `suffix` to its name.
+    pub fn sibling_path(&self, suffix: &str) -> PathBuf {
+        let mut sibling_path = self.path.clone().into_os_string();
+        sibling_path.push(suffix);
+        PathBuf::from(sibling_path)
+    }
+
+    /// Path of a backup, where generation `0` is the most recent one
+    /// (`list.json.bak`) and older ones are numbered (`list.json.bak.1`, ...).
+    pub fn backup_path(&self, generation: usize) -> PathBuf {
+        if generation == 0 {
+            self.sibling_path(".bak")
+        } else {
+            self.sibling_path(&format!(".bak.{generation}"))
+        }
+    }
+
+    /// Loads the list, treating a missing file as an empty list.
+    ///
+    /// The file is remembered even when it can not be parsed, so that saving
+    /// over it is still refused if it changes again.
+    pub fn load(&mut self) -> Result<List, StorageError> {
+        let (stamp, content) = self.read()?;
+        let result = List::try_from_str(&content);
+
+        self.loaded = Some(LoadedState {
+            stamp,
+            list: result.as_ref().ok().cloned(),
+        });
+
+        Ok(result?)
+    }
+
+    /// Loads a backup in place of the file, e.g. because the file is damaged.
+    ///
+    /// The file on disk is remembered as loaded, so the next save replaces it
+    /// without rotating it into the backups over the one that was restored.
+    pub fn load_backup(&mut self, generation: usize) -> Result<List, StorageError> {
+        let list = List::try_from_str(&fs::read_to_string(self.backup_path(generation))?)?;
+        let (stamp, _) = self.read()?;
+
+        self.loaded = Some(LoadedState {
+            stamp,
+            list: Some(list.clone()),
+        });
+        self.backed_up = true;
+
+        Ok(list)
+    }
+
+    /// Saves the list unless the file changed on disk since it was last
+    /// loaded or saved.
+    pub fn save(&mut self, list: &List) -> Result<(), StorageError> {
+        if self.has_changed_on_disk()? {
+            return Err(StorageError::Conflict);
+        }
+
+        self.overwrite(list)
+    }
+
+    /// Saves the list, replacing whatever is on disk.
+    pub fn overwrite(&mut self, list: &List) -> Result<(), StorageError> {
+        let content = list.into_string();
+
+        if !self.backed_up || self.has_changed_on_disk()? {
+            self.rotate_backups()?;
+            self.backed_up = true;
+        }
+
+        write_atomically(&self.path, content.as_bytes())?;
+
+        self.loaded = Some(LoadedState {
+            stamp: Some(FileStamp {
+                modified: fs::metadata(&self.path)?.modified().ok(),
+                hash: hash_content(&content),
+            }),
+            list: Some(list.clone()),
+        });
+
+        Ok(())
+    }
+
+    /// Merges `ours` with the list currently on disk, using the last loaded
+    /// or saved version as the common ancestor.
+    ///
+    /// The file on disk becomes the new base, so saving the merged list
+    /// afterwards succeeds unless the file changes yet again.
+    pub fn merge(&mut self, ours: &List) -> Result<MergeResult, StorageError> {
+        let (stamp, content) = self.read()?;
+        let theirs = List::try_from_str(&content)?;
+
+        let base = match &self.loaded {
+            Some(LoadedState {
+                list: Some(base), ..
+            }) => base.clone(),
+            _ => List::new(),
+        };
+
+        let result = merge::merge(&base, ours, &theirs);
+
+        self.loaded = Some(LoadedState {
+            stamp,
+            list: Some(theirs),
+        });
+
+        Ok(result)
+    }
+
+    /// Whether the file was changed by something else since it was last
+    /// loaded or saved. Always false for a file that was never loaded.
+    pub fn has_changed_on_disk(&self) -> Result<bool, StorageError> {
+        let Some(loaded) = &self.loaded else {
+            return Ok(false);
+        };
+
+        let modified = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.modified().ok(),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok(loaded.stamp.is_some());
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        match &loaded.stamp {
+            Some(stamp) if modified.is_some() && stamp.modified == modified => Ok(false),
+            stamp => Ok(self.read()?.0.as_ref() != stamp.as_ref()),
+        }
+    }
+
+    /// Saves the undo history next to the list, tied to the list as it was
+    /// last loaded or saved.
+    pub fn save_history(&self, history: &History) -> io::Result<()> {
+        let saved = SavedHistory {
+            list_hash: self.loaded_hash(),
+            history,
+        };
+
+        write_atomically(
+            &self.sibling_path(Self::HISTORY_SUFFIX),
+            serde_json::to_string(&saved).unwrap().as_bytes(),
+        )
+    }
+
+    /// Loads the undo history kept next to the list. A history saved with a
+    /// different version of the list, such as one changed outside `jl`
+    /// since, is ignored as its changes would no longer apply.
+    pub fn load_history(&self) -> Result<Option<History>, StorageError> {
+        let content = match fs::read_to_string(self.sibling_path(Self::HISTORY_SUFFIX)) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        let saved: SavedHistory<History> =
+            serde_json::from_str(&content).map_err(LoadError::from)?;
+
+        Ok(
+            (saved.list_hash.is_some() && saved.list_hash == self.loaded_hash())
+                .then_some(saved.history),
+        )
+    }
+
+    /// Removes the undo history kept next to the list, if there is one.
+    pub fn remove_history(&self) -> io::Result<()> {
+        match fs::remove_file(self.sibling_path(Self::HISTORY_SUFFIX)) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    fn loaded_hash(&self) -> Option<u64> {
+        self.loaded
+            .as_ref()
+            .and_then(|loaded| loaded.stamp.as_ref())
+            .map(|stamp| stamp.hash)
+    }
+
+    /// Takes the advisory lock that tells other `jl` instances this list is
+    /// open.
+    pub fn lock(&self) -> Result<LockFile, StorageError> {
+        LockFile::acquire(self.sibling_path(".lock"))
+    }
+
+    fn read(&self) -> io::Result<(Option<FileStamp>, String)> {
+        let mut file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok((None, String::new()));
+            }
+            Err(error) => return Err(error),
+        };
+
+        let modified = file.metadata()?.modified().ok();
+        let mut content = String::new();
+        io::Read::read_to_string(&mut file, &mut content)?;
+
+        let stamp = FileStamp {
+            modified,
+            hash: hash_content(&content),
+        };
+
+        Ok((Some(stamp), content))
+    }
+
+    fn rotate_backups(&self) -> io::Result<()> {
+        if self.backup_count == 0 || !self.path.exists() {
+            return Ok(());
+        }
+
+        for generation in (1..self.backup_count).rev() {
+            let older_path = self.backup_path(generation - 1);
+            if older_path.exists() {
+                fs::rename(older_path, self.backup_path(generation))?;
+            }
+        }
+
+        fs::copy(&self.path, self.backup_path(0))?;
+        Ok(())
+    }
+}
+
+fn hash_content(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// An advisory lock file next to the list holding the id of the process
+/// that has it open. The lock is released when this is dropped.
+///
+/// A lock left behind by a process that is no longer running is taken over.
+pub struct LockFile {
+    path: PathBuf,
+}
+
+impl LockFile {
+    pub fn acquire(path: PathBuf) -> Result<LockFile, StorageError> {
+        for _ in 0..2 {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    write!(file, "{}", std::process::id())?;
+                    return Ok(LockFile { path });
+                }
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                    let pid = fs::read_to_string(&path)
+                        .ok()
+                        .and_then(|content| content.trim().parse::<u32>().ok());
+
+                    match pid {
+                        Some(pid) if is_process_running(pid) => {
+                            return Err(StorageError::Locked { pid });
+                        }
+                        _ => _ = fs::remove_file(&path),
+                    }
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("Unable to take the lock {}", path.display()),
+        )
+        .into())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_process_running(pid: u32) -> bool {
+    Path::new(&format!("/proc/{pid}")).exists()
+}
+
+/// Without a cheap way to check, every lock is assumed to be held.
+#[cfg(not(target_os = "linux"))]
+fn is_process_running(_pid: u32) -> bool {
+    true
+}
+
+/// Replaces the content of `path` without ever leaving it half written: the
+/// data is written and synced to a uniquely named temporary file next to it,
+/// which takes over the permissions of `path` and is then renamed over it.
+/// The directory is synced afterwards so the rename itself survives a crash.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    // The temporary file is removed when dropped, including on errors.
+    let mut file = tempfile::NamedTempFile::new_in(directory)?;
+    file.write_all(contents)?;
+
+    if let Ok(metadata) = fs::metadata(path) {
+        file.as_file().set_permissions(metadata.permissions())?;
+    }
+
+    file.as_file().sync_all()?;
+    file.persist(path).map_err(|error| error.error)?;
+
+    sync_directory(directory)
+}
+
+#[cfg(unix)]
+fn sync_directory(directory: &Path) -> io::Result<()> {
+    fs::File::open(directory)?.sync_all()
+}
+
+/// Directories can not be opened and synced like files everywhere else.
+#[cfg(not(unix))]
+fn sync_directory(_directory: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io(io::Error),
+    Load(LoadError),
+    /// The file was changed by something else since it was last loaded or
+    /// saved.
+    Conflict,
+    /// Another running process holds the lock on the file.
+    Locked {
+        pid: u32,
+    },
+}
+
+impl From<io::Error> for StorageError {
+    fn from(error: io::Error) -> Self {
+        StorageError::Io(error)
+    }
+}
+
+impl From<LoadError> for StorageError {
+    fn from(error: LoadError) -> Self {
+        StorageError::Load(error)
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(error) => write!(f, "{error}"),
+            StorageError::Load(error) => write!(f, "{error}"),
+            StorageError::Conflict => write!(f, "The file was changed by another program"),
+            StorageError::Locked { pid } => {
+                write!(f, "The list is already open in another process ({pid})")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
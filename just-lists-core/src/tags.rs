@@ -0,0 +1,92 @@
+/// Splits the `#tag` tokens out of text typed for an item.
+///
+/// Returns the text without the tags and the spaces before them, keeping
+/// line breaks and any other whitespace, and leaving out lines that held only
+/// tags. The tags come in the order they appeared, without the `#` and
+/// without duplicates. A `#` followed only by digits, such as `#42`, is kept
+/// as text since it usually refers to an issue or a number.
+pub fn extract_tags(text: &str) -> (String, Vec<String>) {
+    let mut tags: Vec<String> = Vec::new();
+
+    let lines: Vec<String> = text
+        .split('\n')
+        .filter_map(|line| remove_tags(line, &mut tags))
+        .collect();
+
+    (lines.join("\n"), tags)
+}
+
+/// Removes the tags from a line, adding them to `tags`. `None` if the line
+/// held nothing but tags.
+fn remove_tags(line: &str, tags: &mut Vec<String>) -> Option<String> {
+    let mut rest = line.trim_start();
+    let mut kept = line[..line.len() - rest.len()].to_string();
+    // The spaces after the last word kept, written once another one follows.
+    let mut spaces = "";
+    let mut words_kept = false;
+    let mut ends_with_tag = false;
+
+    while !rest.is_empty() {
+        let (word, after) = rest.split_at(rest.find(char::is_whitespace).unwrap_or(rest.len()));
+        rest = after.trim_start();
+
+        ends_with_tag = match word.strip_prefix('#').filter(|tag| is_valid_tag(tag)) {
+            Some(tag) => {
+                if !tags.iter().any(|t| same_tag(t, tag)) {
+                    tags.push(tag.to_string());
+                }
+
+                true
+            }
+            None => {
+                if words_kept {
+                    kept.push_str(spaces);
+                }
+
+                kept.push_str(word);
+                spaces = &after[..after.len() - rest.len()];
+                words_kept = true;
+                false
+            }
+        };
+    }
+
+    if !ends_with_tag {
+        kept.push_str(spaces);
+    }
+
+    (words_kept || !ends_with_tag).then_some(kept)
+}
+
+/// Parses a list of tags separated by spaces or commas, with or without a
+/// leading `#`. Invalid tags are ignored.
+pub fn parse_tag_list(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+
+    for tag in text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .map(|tag| tag.strip_prefix('#').unwrap_or(tag))
+        .filter(|tag| is_valid_tag(tag))
+    {
+        if !tags.iter().any(|t| same_tag(t, tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+
+    tags
+}
+
+/// Whether two tags are the same, ignoring case in any script.
+pub fn same_tag(a: &str, b: &str) -> bool {
+    a == b || a.to_lowercase() == b.to_lowercase()
+}
+
+/// Tags are made of letters, digits, `-`, `_` and `/`, and are not only
+/// digits.
+pub fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '/'))
+        && !tag.chars().all(|c| c.is_ascii_digit())
+}
@@ -0,0 +1,129 @@
+use crate::list_item::State;
+use std::fmt;
+
+/// How a [`State`] is shown, and whether it counts as finished.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateDefinition {
+    pub state: State,
+    /// Shown in front of the item.
+    pub glyph: String,
+    /// The colour of the item, as a colour name or `#rrggbb`.
+    pub color: String,
+    /// The background of the item while it is selected. Defaults to `color`.
+    pub highlight: Option<String>,
+    /// Items in a finished state are never overdue.
+    pub done: bool,
+}
+
+impl StateDefinition {
+    pub fn new(state: State, glyph: &str, color: &str) -> StateDefinition {
+        StateDefinition {
+            state,
+            glyph: glyph.to_string(),
+            color: color.to_string(),
+            highlight: None,
+            done: false,
+        }
+    }
+}
+
+/// The states items move through, in the order toggling an item cycles
+/// through them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Workflow {
+    states: Vec<StateDefinition>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WorkflowError {
+    NoStates,
+    EmptyName,
+    DuplicateState(String),
+}
+
+impl fmt::Display for WorkflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkflowError::NoStates => write!(f, "At least one state must be declared"),
+            WorkflowError::EmptyName => write!(f, "State names must not be empty"),
+            WorkflowError::DuplicateState(name) => {
+                write!(f, "The state `{name}` is declared more than once")
+            }
+        }
+    }
+}
+
+impl std::error::Error for WorkflowError {}
+
+impl Default for Workflow {
+    /// Pending, Completed and Blocked, as `jl` has always had them.
+    fn default() -> Workflow {
+        Workflow {
+            states: vec![
+                StateDefinition {
+                    highlight: Some("#9eaaf8".to_string()),
+                    ..StateDefinition::new(State::Pending, "☐", "gray")
+                },
+                StateDefinition {
+                    done: true,
+                    ..StateDefinition::new(State::Completed, "✔", "green")
+                },
+                StateDefinition::new(State::Blocked, "⛔", "red"),
+            ],
+        }
+    }
+}
+
+impl Workflow {
+    pub fn new(states: Vec<StateDefinition>) -> Result<Workflow, WorkflowError> {
+        if states.is_empty() {
+            return Err(WorkflowError::NoStates);
+        }
+
+        for (index, definition) in states.iter().enumerate() {
+            if definition.state.name().is_empty() {
+                return Err(WorkflowError::EmptyName);
+            }
+
+            if states[..index].iter().any(|d| d.state == definition.state) {
+                return Err(WorkflowError::DuplicateState(
+                    definition.state.name().to_string(),
+                ));
+            }
+        }
+
+        Ok(Workflow { states })
+    }
+
+    pub fn states(&self) -> &[StateDefinition] {
+        &self.states
+    }
+
+    /// The definition of `state`, if the workflow declares it. Lists may hold
+    /// states from another configuration.
+    pub fn definition(&self, state: &State) -> Option<&StateDefinition> {
+        self.states.iter().find(|d| d.state == *state)
+    }
+
+    /// The state after `state`, wrapping around at the end. Undeclared states
+    /// move to the first one.
+    pub fn next(&self, state: &State) -> State {
+        let next_index = self
+            .states
+            .iter()
+            .position(|d| d.state == *state)
+            .map_or(0, |index| (index + 1) % self.states.len());
+
+        self.states[next_index].state.clone()
+    }
+
+    /// The first finished state, which items move to when they are
+    /// completed automatically.
+    pub fn completed_state(&self) -> Option<&State> {
+        self.states.iter().find(|d| d.done).map(|d| &d.state)
+    }
+
+    pub fn is_done(&self, state: &State) -> bool {
+        self.definition(state).is_some_and(|d| d.done)
+    }
+}
@@ -1,15 +1,29 @@
+use chrono::{Local, NaiveDate, Utc};
 use color_eyre::{Result, eyre::Ok};
 use core::panic;
-use crossterm::event::{self, Event, KeyCode};
-use just_lists_core::list_item::State;
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use just_lists_core::dates::parse_date;
+use just_lists_core::history::{Command, CommandError, History};
+use just_lists_core::list::Progress;
+use just_lists_core::list_item::{Priority, State};
+use just_lists_core::outline::{parse_outline, write_outline};
+use just_lists_core::search::{SearchResult, search};
+use just_lists_core::storage::{LockFile, Storage, StorageError};
+use just_lists_core::tags::{extract_tags, parse_tag_list};
 use just_lists_core::{get_sample_list, list::List};
 use ratatui::widgets::{ListState, Scrollbar, ScrollbarState};
 use ratatui::{prelude::*, widgets::BorderType};
-use std::collections::HashSet;
-use std::io::Read;
+use std::collections::{HashMap, HashSet};
+use std::fs;
 use std::path::PathBuf;
 use std::time::Duration;
-use std::{fs, i32};
+
+use crate::config::Config;
+use crate::editor::TextEditor;
+use crate::keymap::KeyReader;
+use crate::markdown;
+use crate::view::{SortMode, ViewOptions};
+use crate::watch::FileWatcher;
 
 use ratatui::{
     DefaultTerminal, Frame,
@@ -18,11 +32,31 @@ use ratatui::{
     widgets::{Block, Borders, Clear, List as WidgetList, ListItem},
 };
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Inputs {
     pub file: Option<PathBuf>,
+    /// Keep the undo history in a file next to the list so it survives restarts
+    #[arg(long)]
+    pub keep_history: bool,
+    /// Read settings from this file instead of the user's config directory
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Check a list file for broken references, orphaned items and cycles
+    Check {
+        file: PathBuf,
+        /// Repair the problems that were found and save the file
+        #[arg(long)]
+        fix: bool,
+    },
 }
 
 #[derive(Clone)]
@@ -31,31 +65,137 @@ struct ListEntry {
     expanded: bool,
 }
 
+#[allow(clippy::enum_variant_names)]
 enum UIState {
     ListView,
     EditView,
+    NotesView,
+    RecoveryView,
+    ConflictView,
+    PromptView(Prompt),
+    SearchView,
+    ArchiveView,
+    PasteView,
+    LocationsView,
+}
+
+/// Where a pasted item goes, relative to the selected item.
+#[derive(Clone, Copy, PartialEq)]
+enum PasteTarget {
+    Child,
+    Before,
+    After,
+    TopLevel,
+}
+
+/// A single line of input asked for in a popup.
+#[derive(Clone, Copy, PartialEq)]
+enum Prompt {
+    DueDate,
+    ScheduledDate,
+    Tags,
+    TagFilter,
+}
+
+impl Prompt {
+    fn title(self) -> &'static str {
+        match self {
+            Prompt::DueDate => "Due date",
+            Prompt::ScheduledDate => "Scheduled date",
+            Prompt::Tags => "Tags",
+            Prompt::TagFilter => "Filter by tag",
+        }
+    }
+
+    /// Whether the prompt edits the selected item.
+    fn needs_item(self) -> bool {
+        !matches!(self, Prompt::TagFilter)
+    }
 }
 
-#[derive(Debug)]
-enum Message {
+#[derive(Debug, Clone)]
+pub(crate) enum Message {
     Up,
     Down,
+    First,
+    Last,
+    PreviousSibling,
+    NextSibling,
+    /// Repeats the last change.
+    Repeat,
     Esc,
     Edit,
+    /// Opens the item's text in the user's text editor.
+    EditInEditor,
+    /// Opens the item and everything below it in the user's text editor.
+    OutlineInEditor,
+    EditNotes,
+    ToggleNotes,
     Enter,
     Space,
-    Left,
-    Right,
-    Backspace,
     New,
     InsertChild,
     Delete,
+    DeleteEverywhere,
     FocusOnCurrentItem,
     FocusOnParentItem,
     Copy,
     Cut,
     Paste,
+    Undo,
+    Redo,
+    OpenBackup,
+    StartReadOnly,
+    Save,
+    Reload,
+    Overwrite,
+    Merge,
+    SetDueDate,
+    SetScheduledDate,
+    EditTags,
+    FilterByTag,
+    RaisePriority,
+    LowerPriority,
+    CycleSort,
+    CycleDueFilter,
+    Search,
+    ToggleHideCompleted,
+    ToggleBlockedOnly,
+    Archive,
+    OpenArchive,
+    MoveUp,
+    MoveDown,
+    Indent,
+    Outdent,
+    PasteMenu,
+    ShowLocations,
+    Unshare,
     Text(char),
+    /// A key for the text being edited.
+    EditorKey(event::KeyEvent),
+    /// Text pasted into the terminal.
+    PasteText(String),
+}
+
+impl Message {
+    /// Whether the message changes the list, so that it can be repeated.
+    fn is_change(&self) -> bool {
+        matches!(
+            self,
+            Message::Space
+                | Message::Delete
+                | Message::DeleteEverywhere
+                | Message::Paste
+                | Message::Unshare
+                | Message::MoveUp
+                | Message::MoveDown
+                | Message::Indent
+                | Message::Outdent
+                | Message::RaisePriority
+                | Message::LowerPriority
+                | Message::Archive
+        )
+    }
 }
 
 #[derive(PartialEq)]
@@ -73,52 +213,142 @@ pub struct App {
     list: List,
     state: UIState,
     selected_list_index: usize,
-    cursor_index: i32,
     display: Vec<ListEntry>,
-    edit_text: String,
-    file_path: Option<PathBuf>,
+    editor: TextEditor,
+    storage: Option<Storage>,
     display_parent_item: Option<Vec<String>>,
     clipboard: Option<Clipboard>,
     debug: bool,
     expanded_items: HashSet<Vec<String>>,
+    load_error: Option<String>,
+    read_only: bool,
+    status_message: Option<String>,
+    save_error: Option<String>,
+    save_conflict: bool,
+    _lock: Option<LockFile>,
+    watcher: Option<FileWatcher>,
+    reload_pending: bool,
+    view: ViewOptions,
+    history: History,
+    keep_history: bool,
+    config: Config,
+    search_results: Vec<SearchResult>,
+    selected_search_result: usize,
+    /// Counted from the most recently archived item.
+    selected_archive_index: usize,
+    /// Every place the selected item appears in, while they are shown.
+    locations: Vec<Vec<String>>,
+    selected_location: usize,
+    keys: KeyReader,
+    last_change: Option<(Message, usize)>,
+    /// Set while a change repeated by a count runs, so it is saved once.
+    defer_saves: bool,
+    /// Whether the notes of the selected item are shown next to the list.
+    show_notes: bool,
+    /// The progress of the items on display, worked out when the display
+    /// changes rather than on every frame.
+    progress: HashMap<String, Progress>,
 }
 
 impl App {
-    pub fn new(file: Option<PathBuf>) -> App {
+    pub fn new(file: Option<PathBuf>, keep_history: bool, config: Config) -> App {
         let mut list: List;
-        if file.is_some() {
-            list = List::from_string(App::open_or_create_file(&file.clone().unwrap()));
+        let mut load_error: Option<String> = None;
+        let mut read_only = false;
+        let mut status_message: Option<String> = None;
+        let mut lock: Option<LockFile> = None;
+        let mut watcher: Option<FileWatcher> = None;
+
+        let mut storage = file.map(Storage::new);
+
+        if let Some(storage) = &mut storage {
+            match storage.lock() {
+                std::result::Result::Ok(lock_file) => lock = Some(lock_file),
+                Err(error @ StorageError::Locked { .. }) => {
+                    read_only = true;
+                    status_message = Some(format!("{error}, opened read-only"));
+                }
+                Err(error) => status_message = Some(format!("Unable to lock the list: {error}")),
+            }
+
+            match FileWatcher::new(storage.path()) {
+                std::result::Result::Ok(file_watcher) => watcher = Some(file_watcher),
+                Err(error) => {
+                    status_message.get_or_insert(format!(
+                        "Unable to watch the list for outside changes: {error}"
+                    ));
+                }
+            }
+
+            match storage.load() {
+                std::result::Result::Ok(loaded_list) => list = loaded_list,
+                Err(error) => {
+                    load_error = Some(error.to_string());
+                    list = List::new();
+                }
+            }
         } else {
             list = get_sample_list();
         }
 
-        let list_is_empty = list.get_top_level_list_items().len() == 0;
+        let list_is_empty = list.get_top_level_list_items().is_empty();
 
-        if list_is_empty {
+        if list_is_empty && load_error.is_none() {
             list.add_list_item(
                 just_lists_core::list_item::ListItem::new("".to_string()),
                 None,
             );
         }
 
-        let app = App {
-            list: list,
-            state: if !list_is_empty {
+        let mut app = App {
+            list,
+            state: if load_error.is_some() {
+                UIState::RecoveryView
+            } else if !list_is_empty {
                 UIState::ListView
             } else {
                 UIState::EditView
             },
             selected_list_index: 0,
             display: Vec::new(),
-            cursor_index: 0,
-            edit_text: "".to_string(),
-            file_path: file,
+            editor: TextEditor::new(""),
+            storage,
             display_parent_item: None,
             clipboard: None,
             debug: false,
             expanded_items: HashSet::new(),
+            load_error,
+            read_only,
+            status_message,
+            save_error: None,
+            save_conflict: false,
+            _lock: lock,
+            watcher,
+            reload_pending: false,
+            view: ViewOptions::new(),
+            history: History::new(),
+            keep_history,
+            config,
+            search_results: Vec::new(),
+            selected_search_result: 0,
+            selected_archive_index: 0,
+            locations: Vec::new(),
+            selected_location: 0,
+            keys: KeyReader::default(),
+            last_change: None,
+            defer_saves: false,
+            show_notes: true,
+            progress: HashMap::new(),
         };
 
+        if app.keep_history && app.load_error.is_none() {
+            app.load_history();
+        }
+
+        if app.status_message.is_none() {
+            app.status_message = app.due_reminder();
+        }
+
         app
     }
 
@@ -129,33 +359,148 @@ impl App {
             terminal.draw(|f| self.view(f))?;
             let current_msg = self.handle_event()?;
 
+            if current_msg.is_some() {
+                self.status_message = None;
+            }
+
+            if self
+                .watcher
+                .as_ref()
+                .is_some_and(|watcher| watcher.has_changed())
+            {
+                self.reload_pending = true;
+            }
+
+            // Wait for any edit in progress to finish before reloading.
+            if self.reload_pending && matches!(self.state, UIState::ListView) {
+                self.reload_pending = false;
+                self.reload_if_changed_on_disk();
+            }
+
+            if self.save_conflict && matches!(self.state, UIState::ListView) {
+                self.state = UIState::ConflictView;
+            }
+
             match self.state {
                 UIState::ListView => match current_msg {
                     Some(Message::Esc) => return Ok(()),
-                    Some(Message::Down) => self.handle_scroll(Message::Down),
-                    Some(Message::Up) => self.handle_scroll(Message::Up),
-                    Some(Message::Enter) => self.handle_expand(),
-                    Some(Message::FocusOnCurrentItem) => self.focus_on_current(),
-                    Some(Message::FocusOnParentItem) => self.focus_on_parent(),
-                    Some(Message::Copy) => self.copy(),
-                    Some(Message::Cut) => self.cut(),
-                    Some(Message::Paste) => self.paste(),
-                    Some(Message::Delete) => self.delete_selected_item(),
-                    Some(Message::New) => self.add_new_list_item(),
-                    Some(Message::InsertChild) => self.insert_child_item(),
-                    Some(Message::Space) => self.toggle_item_completion(),
-                    Some(Message::Edit) => self.toggle_edit_mode(),
-                    Some(Message::Text(c)) => self.handle_text_input(c),
+                    Some(Message::Repeat) => self.repeat_last_change(),
+                    Some(Message::EditInEditor) => self.edit_externally(&mut terminal, false),
+                    Some(Message::OutlineInEditor) => self.edit_externally(&mut terminal, true),
+                    Some(message) => {
+                        let count = self.keys.take_count();
+                        self.handle_list_message(message, count);
+                    }
                     None => (),
-                    _ => (),
                 },
                 UIState::EditView => match current_msg {
                     Some(Message::Esc) => self.state = UIState::ListView,
                     Some(Message::Enter) => self.save_edited_text(),
-                    Some(Message::Left) => self.handle_cursor_left(),
-                    Some(Message::Right) => self.handle_cursor_right(),
-                    Some(Message::Backspace) => self.handle_backspace(),
-                    Some(Message::Text(c)) => self.handle_text_input(c),
+                    Some(Message::EditorKey(key)) => {
+                        self.editor.handle_key(key, self.editor_width());
+                    }
+                    Some(Message::PasteText(text)) => self.editor.paste(&text),
+                    _ => (),
+                },
+                UIState::NotesView => match current_msg {
+                    Some(Message::Esc) => self.state = UIState::ListView,
+                    Some(Message::Enter) => self.save_notes(),
+                    Some(Message::EditorKey(key)) => {
+                        self.editor.handle_key(key, self.editor_width());
+                    }
+                    Some(Message::PasteText(text)) => self.editor.paste(&text),
+                    _ => (),
+                },
+                UIState::RecoveryView => match current_msg {
+                    Some(Message::Esc) => return Ok(()),
+                    Some(Message::OpenBackup) => self.open_backup(),
+                    Some(Message::StartReadOnly) => self.start_read_only(),
+                    _ => (),
+                },
+                UIState::ConflictView => match current_msg {
+                    Some(Message::Esc) | Some(Message::FocusOnParentItem) => {
+                        self.save_conflict = false;
+                        self.state = UIState::ListView;
+                    }
+                    Some(Message::Reload) => self.reload_from_disk(),
+                    Some(Message::Overwrite) => self.overwrite_file(),
+                    Some(Message::Merge) => self.merge_with_file(),
+                    _ => (),
+                },
+                UIState::PromptView(prompt) => match current_msg {
+                    Some(Message::Esc) | Some(Message::FocusOnParentItem) => {
+                        self.state = UIState::ListView
+                    }
+                    Some(Message::Enter) => self.submit_prompt(prompt),
+                    Some(Message::EditorKey(key)) => {
+                        self.editor.handle_key(key, self.editor_width());
+                    }
+                    Some(Message::PasteText(text)) => self.editor.paste(&text),
+                    _ => (),
+                },
+                UIState::LocationsView => match current_msg {
+                    Some(Message::Esc) | Some(Message::FocusOnParentItem) => {
+                        self.state = UIState::ListView
+                    }
+                    Some(Message::Enter) => {
+                        self.state = UIState::ListView;
+                        if let Some(id_path) = self.locations.get(self.selected_location) {
+                            self.jump_to(id_path.clone());
+                        }
+                    }
+                    Some(Message::Up) => {
+                        self.selected_location = self.selected_location.saturating_sub(1)
+                    }
+                    Some(Message::Down) => {
+                        self.selected_location =
+                            (self.selected_location + 1).min(self.locations.len().saturating_sub(1))
+                    }
+                    _ => (),
+                },
+                UIState::PasteView => match current_msg {
+                    Some(Message::Esc) | Some(Message::FocusOnParentItem) => {
+                        self.state = UIState::ListView
+                    }
+                    Some(Message::Text(c)) => self.paste_from_menu(c),
+                    _ => (),
+                },
+                UIState::ArchiveView => match current_msg {
+                    Some(Message::Esc) | Some(Message::FocusOnParentItem) => {
+                        self.state = UIState::ListView
+                    }
+                    Some(Message::Enter) => self.restore_archived_item(),
+                    Some(Message::Up) => {
+                        self.selected_archive_index = self.selected_archive_index.saturating_sub(1)
+                    }
+                    Some(Message::Down) => {
+                        self.selected_archive_index = (self.selected_archive_index + 1)
+                            .min(self.list.archived_items().len().saturating_sub(1))
+                    }
+                    _ => (),
+                },
+                UIState::SearchView => match current_msg {
+                    Some(Message::Esc) | Some(Message::FocusOnParentItem) => {
+                        self.state = UIState::ListView
+                    }
+                    Some(Message::Enter) => self.jump_to_search_result(),
+                    Some(Message::Up) => {
+                        self.selected_search_result = self.selected_search_result.saturating_sub(1)
+                    }
+                    Some(Message::Down) => {
+                        self.selected_search_result = (self.selected_search_result + 1)
+                            .min(self.search_results.len().saturating_sub(1))
+                    }
+                    Some(Message::EditorKey(key)) => {
+                        let changed = self.editor.handle_key(key, self.editor_width());
+
+                        if changed {
+                            self.update_search();
+                        }
+                    }
+                    Some(Message::PasteText(text)) => {
+                        self.editor.paste(&text);
+                        self.update_search();
+                    }
                     _ => (),
                 },
             }
@@ -165,11 +510,16 @@ impl App {
     const BASE_UI_BORDER_TYPE: BorderType = BorderType::Thick;
     const BASE_UI_COLOR: Color = Color::Rgb(158, 170, 248);
 
-    const SELECTED_INCOMPLETE_STYLE: Style = Style::new().bg(Self::BASE_UI_COLOR).fg(Color::Black);
+    /// Shown for states the configured workflow does not declare.
+    const UNKNOWN_STATE_GLYPH: &str = "?";
 
-    const SELECTED_COMPLETE_STYLE: Style = Style::new().bg(Color::Green).fg(Color::Black);
+    const UNKNOWN_STATE_COLOR: Color = Color::Gray;
 
-    const SELECTED_BLOCKED_STYLE: Style = Style::new().bg(Color::Red).fg(Color::Black);
+    const OVERDUE_COLOR: Color = Color::LightRed;
+
+    const DUE_TODAY_COLOR: Color = Color::Yellow;
+    /// Narrower screens leave the notes out rather than squeeze the list.
+    const NOTES_MIN_WIDTH: u16 = 60;
 
     fn view(&self, frame: &mut Frame) {
         let layout = Layout::default()
@@ -179,11 +529,15 @@ impl App {
 
         let current_path = "/ ".to_string();
 
-        let mut current_file = match self.file_path.clone() {
-            Some(path) => path.to_str().unwrap().to_string(),
+        let mut current_file = match &self.storage {
+            Some(storage) => storage.path().display().to_string(),
             None => "Sample".to_string(),
         };
 
+        if self.read_only {
+            current_file.push_str(" (Read-only)");
+        }
+
         current_file.insert(0, '[');
         current_file.insert(current_file.len(), ']');
 
@@ -204,7 +558,7 @@ impl App {
             Some(parent_item) => {
                 let mut path = current_path;
                 for id in parent_item {
-                    let item = self.list.get_list_item(&id).unwrap();
+                    let item = self.list.get_list_item(id).unwrap();
                     path.push_str(item.value.clone().as_str());
                     path.push_str(" / ");
                 }
@@ -259,7 +613,7 @@ impl App {
 
         frame.render_widget(title_paragraph, title_layout);
 
-        let block = Block::new()
+        let mut block = Block::new()
             .title("")
             .title("[List]")
             .title_style(Style::default().add_modifier(Modifier::BOLD))
@@ -267,31 +621,70 @@ impl App {
             .border_style(Self::BASE_UI_COLOR)
             .borders(Borders::ALL);
 
+        for description in self.view.describe() {
+            block = block.title(description);
+        }
+
+        let today = App::today();
+
+        if let Some(save_error) = &self.save_error {
+            block = block.title_bottom(
+                Line::from(format!("[Save failed: {save_error} (Ctrl-s to retry)]"))
+                    .style(Style::new().fg(Color::Red)),
+            );
+        }
+
+        if let Some(status_message) = &self.status_message {
+            block = block.title_bottom(
+                Line::from(format!("[{status_message}]")).style(Style::new().fg(Color::Yellow)),
+            );
+        }
+
+        if let Some(typed) = self.keys.typed() {
+            block = block.title_bottom(Line::from(format!("[{typed}]")).right_aligned());
+        }
+
+        let notes = self
+            .get_current_display_item()
+            .and_then(|entry| self.list.get_list_item(entry.id_path.last().unwrap()))
+            .and_then(|item| item.notes.as_deref())
+            .filter(|_| self.show_notes && layout[1].width >= Self::NOTES_MIN_WIDTH);
+
+        let [list_area, notes_area] = match notes {
+            Some(_) => Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(layout[1]),
+            None => [layout[1], Rect::default()],
+        };
+
         let list_layout = Layout::default()
             .direction(Direction::Horizontal)
             .constraints(vec![Constraint::Percentage(100), Constraint::Length(1)])
-            .split(layout[1]);
+            .split(list_area);
 
         let list_box_text_width: usize = (list_layout[0].width - 2).into();
 
+        let reference_counts = self.list.reference_counts();
+
         let items: Vec<ListItem> = self
             .display
             .iter()
             .enumerate()
             .map(|(i, todo_item)| {
-                let text: String;
                 let list_item = self
                     .list
                     .get_list_item(todo_item.id_path.last().unwrap())
                     .unwrap();
 
-                let (color, check_box_state) = match list_item.state {
-                    State::Pending => (Color::Gray, "☐ "),
-                    State::Completed => (Color::Green, "✔ "),
-                    State::Blocked => (Color::Red, "⛔ "),
-                };
+                let (glyph, mut color, highlight) = self.state_style(&list_item.state);
+                let check_box_state = format!("{glyph} ");
+
+                if list_item.is_overdue(today, &self.config.workflow) {
+                    color = Self::OVERDUE_COLOR;
+                } else if list_item.is_due_today(today, &self.config.workflow) {
+                    color = Self::DUE_TODAY_COLOR;
+                }
 
-                let children_count = self.list.get_children(list_item).len();
+                let children_count = self.visible_children(list_item, today).len();
                 let expandable_symbol_text = if children_count > 0 {
                     if todo_item.expanded {
                         "▼ ".to_string()
@@ -315,11 +708,47 @@ impl App {
                         0
                     };
 
-                text = format!(
-                    "{}{}{}{}",
+                let tags_text: String = list_item
+                    .tags
+                    .iter()
+                    .map(|tag| format!(" #{tag}"))
+                    .collect();
+
+                let priority_marker = match list_item.priority {
+                    Priority::None => "",
+                    Priority::Low => "↓ ",
+                    Priority::Medium => "! ",
+                    Priority::High => "!! ",
+                    Priority::Urgent => "!!! ",
+                };
+
+                let shared_marker = match reference_counts.get(list_item.id.as_str()) {
+                    Some(&count) if count > 1 => format!(" ⇄{count}"),
+                    _ => "".to_string(),
+                };
+
+                let notes_marker = if list_item.notes.is_some() {
+                    " ✎"
+                } else {
+                    ""
+                };
+
+                let progress_text = match self.progress.get(&list_item.id) {
+                    Some(progress) => format!(" [{}/{}]", progress.done, progress.total),
+                    None => "".to_string(),
+                };
+
+                let text: String = format!(
+                    "{}{}{}{}{}{}{}{}{}{}",
                     expandable_symbol_text,
                     check_box_state,
+                    priority_marker,
                     list_item.value.clone(),
+                    notes_marker,
+                    shared_marker,
+                    progress_text,
+                    tags_text,
+                    App::dates_text(list_item, today),
                     debug_text
                 );
 
@@ -345,23 +774,25 @@ impl App {
                     .initial_indent(&initial_space_indent)
                     .subsequent_indent(&subsequent_space_indent);
                 let text = textwrap::fill(&text, options);
-                let mut item = ListItem::from(text).style(color);
+
+                // Tags keep the item's colours while it is selected so they
+                // stay readable on the highlight.
+                let mut item = if i == self.selected_list_index {
+                    ListItem::from(text)
+                } else {
+                    ListItem::from(Text::from(
+                        text.lines()
+                            .map(|line| App::color_tags(line, list_item))
+                            .collect::<Vec<Line>>(),
+                    ))
+                }
+                .style(color);
 
                 if i == self.selected_list_index {
-                    match list_item.state {
-                        State::Completed => {
-                            item = item.style(Self::SELECTED_COMPLETE_STYLE);
-                        }
-                        State::Pending => {
-                            item = item.style(Self::SELECTED_INCOMPLETE_STYLE);
-                        }
-                        State::Blocked => {
-                            item = item.style(Self::SELECTED_BLOCKED_STYLE);
-                        }
-                    }
+                    item = item.style(Style::new().bg(highlight).fg(Color::Black));
                 }
 
-                return item;
+                item
             })
             .collect();
 
@@ -380,7 +811,7 @@ impl App {
             ])
             .split(list_layout[1]);
 
-        frame.render_stateful_widget(list, layout[1], &mut list_state);
+        frame.render_stateful_widget(list, list_area, &mut list_state);
 
         let mut scrollbar_state = ScrollbarState::default()
             .content_length(self.display.len())
@@ -395,448 +826,1938 @@ impl App {
         frame.render_widget(Clear, scroll_bar_layout[1]);
         frame.render_stateful_widget(scrollbar, scroll_bar_layout[1], &mut scrollbar_state);
 
-        match self.state {
-            UIState::EditView => {
-                let block = Block::new()
-                    .title("Edit")
-                    .border_type(Self::BASE_UI_BORDER_TYPE)
-                    .border_style(Self::BASE_UI_COLOR)
-                    .borders(Borders::ALL);
-                let area = Self::popup_area(frame.area(), 60, 20);
-
-                let text_edit_horizon: usize = (area.width - 4).into();
-                let text_edit_vertical: usize = (area.height - 2).into();
-
-                let options = textwrap::Options::new(text_edit_horizon);
-                let text_wrapped_collection = textwrap::wrap(&self.edit_text, options);
-                let wrapped_content = text_wrapped_collection.join("\n");
-
-                let mut column_number = 0;
-                let mut line_number = 0;
-                let mut current_index_number = 0;
-
-                for c in wrapped_content.chars() {
-                    if c == '\n' {
-                        line_number += 1;
-                        column_number = 0;
-                    } else {
-                        column_number += 1;
-                    }
+        if let Some(notes) = notes {
+            let notes_block = Block::new()
+                .title("")
+                .title("[Notes]")
+                .title_style(Style::default().add_modifier(Modifier::BOLD))
+                .border_type(Self::BASE_UI_BORDER_TYPE)
+                .border_style(Self::BASE_UI_COLOR)
+                .borders(Borders::ALL);
 
-                    if current_index_number == self.cursor_index {
-                        break;
-                    }
+            frame.render_widget(
+                Paragraph::new(markdown::render(notes))
+                    .block(notes_block)
+                    .wrap(ratatui::widgets::Wrap { trim: false }),
+                notes_area,
+            );
+        }
 
-                    current_index_number += 1;
-                }
+        if let UIState::EditView = self.state {
+            let block = Block::new()
+                .title("Edit")
+                .title_bottom("[Enter] Save [Shift-Enter] New line [Esc] Cancel")
+                .border_type(Self::BASE_UI_BORDER_TYPE)
+                .border_style(Self::BASE_UI_COLOR)
+                .borders(Borders::ALL);
+            let area = Self::editor_popup_area(frame.area(), &UIState::EditView);
+            let inner_area = block.inner(area);
 
-                let text_line_offset =
-                    (line_number as i32 + 1 - text_edit_vertical as i32).clamp(0, i32::MAX);
+            frame.render_widget(Clear, area);
+            frame.render_widget(block, area);
+            self.render_editor(frame, inner_area);
+        }
 
-                line_number -= text_line_offset;
+        if let UIState::NotesView = self.state {
+            let block = Block::new()
+                .title("[Notes]")
+                .title_style(Style::default().add_modifier(Modifier::BOLD))
+                .title_bottom("[Ctrl-s] Save [Enter] New line [Esc] Cancel")
+                .border_type(Self::BASE_UI_BORDER_TYPE)
+                .border_style(Self::BASE_UI_COLOR)
+                .borders(Borders::ALL);
+            let area = Self::editor_popup_area(frame.area(), &UIState::NotesView);
+            let inner_area = block.inner(area);
 
-                let final_edit_display_text: String = text_wrapped_collection
-                    .into_iter()
-                    .skip(text_line_offset as usize)
-                    .collect::<Vec<_>>()
-                    .join("\n");
+            frame.render_widget(Clear, area);
+            frame.render_widget(block, area);
+            self.render_editor(frame, inner_area);
+        }
 
-                let edit_content = Paragraph::new(final_edit_display_text).block(block);
-                frame.render_widget(Clear, area);
-                frame.render_widget(edit_content, area);
+        if let UIState::RecoveryView = self.state {
+            self.render_recovery_popup(frame);
+        }
 
-                frame.set_cursor_position(Position::new(
-                    area.x + 1 + u16::try_from(column_number).unwrap(),
-                    area.y + 1 + u16::try_from(line_number).unwrap(),
-                ));
-            }
-            _ => (),
+        if let UIState::ConflictView = self.state {
+            self.render_conflict_popup(frame);
         }
-    }
 
-    fn handle_event(&self) -> color_eyre::Result<Option<Message>> {
-        if event::poll(Duration::from_millis(250))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == event::KeyEventKind::Press {
-                    return Ok(self.handle_key(key));
-                }
-            }
+        if let UIState::PromptView(prompt) = self.state {
+            self.render_prompt_popup(frame, prompt);
         }
-        Ok(None)
-    }
 
-    fn handle_key(&self, key: event::KeyEvent) -> Option<Message> {
-        match key.code {
-            KeyCode::Esc => match &self.display_parent_item {
-                None => Some(Message::Esc),
-                Some(path) => {
-                    if path.len() > 0 {
-                        Some(Message::FocusOnParentItem)
-                    } else {
-                        Some(Message::Esc)
-                    }
-                }
-            },
-            KeyCode::Enter => Some(Message::Enter),
-            KeyCode::Up => Some(Message::Up),
-            KeyCode::Down => Some(Message::Down),
-            KeyCode::Left => Some(Message::Left),
-            KeyCode::Right => Some(Message::Right),
-            KeyCode::Backspace => Some(Message::Backspace),
-            KeyCode::Char(c) => match self.state {
-                UIState::ListView => match key.code {
-                    KeyCode::Char(' ') => Some(Message::Space),
-                    KeyCode::Char('e') => Some(Message::Edit),
-                    KeyCode::Char('n') => Some(Message::New),
-                    KeyCode::Char('i') => Some(Message::InsertChild),
-                    KeyCode::Char('d') => Some(Message::Delete),
-                    KeyCode::Char('j') => Some(Message::FocusOnCurrentItem),
-                    KeyCode::Char('c') => Some(Message::Copy),
-                    KeyCode::Char('x') => Some(Message::Cut),
-                    KeyCode::Char('v') => Some(Message::Paste),
-                    _ => None,
-                },
-                UIState::EditView => {
-                    return Some(Message::Text(c));
-                }
-            },
+        if let UIState::SearchView = self.state {
+            self.render_search_popup(frame);
+        }
 
-            _ => None,
+        if let UIState::ArchiveView = self.state {
+            self.render_archive_popup(frame);
         }
-    }
 
-    fn handle_scroll(&mut self, message: Message) {
-        if self.display.len() == 0 {
-            return;
+        if let UIState::PasteView = self.state {
+            self.render_paste_popup(frame);
         }
 
-        match message {
-            Message::Down => {
-                if self.selected_list_index == self.display.len() - 1 {
-                    self.selected_list_index = 0;
-                } else {
-                    self.selected_list_index += 1;
-                }
+        if let UIState::LocationsView = self.state {
+            self.render_locations_popup(frame);
+        }
+    }
+
+    fn render_prompt_popup(&self, frame: &mut Frame, prompt: Prompt) {
+        let block = Block::new()
+            .title(format!("[{}]", prompt.title()))
+            .title_style(Style::default().add_modifier(Modifier::BOLD))
+            .title_bottom("[Enter] Save [Esc] Cancel")
+            .border_type(Self::BASE_UI_BORDER_TYPE)
+            .border_style(Self::BASE_UI_COLOR)
+            .borders(Borders::ALL);
+        let area = Self::editor_popup_area(frame.area(), &UIState::PromptView(prompt));
+
+        let hint = match prompt {
+            Prompt::DueDate | Prompt::ScheduledDate => {
+                "YYYY-MM-DD, today, tomorrow, a weekday (fri), or +3d / +2w / +1m.\n\
+                Leave empty to clear the date."
+                    .to_string()
             }
-            Message::Up => {
-                if self.selected_list_index == 0 {
-                    self.selected_list_index = self.display.len() - 1;
+            Prompt::Tags | Prompt::TagFilter => {
+                let known_tags = self
+                    .list
+                    .all_tags()
+                    .iter()
+                    .map(|tag| format!("#{tag}"))
+                    .collect::<Vec<String>>()
+                    .join(" ");
+
+                let usage = if prompt == Prompt::Tags {
+                    "Separate tags with spaces."
                 } else {
-                    self.selected_list_index -= 1;
-                }
+                    "Leave empty to show every item."
+                };
+
+                format!("{usage}\nTags in this list: {known_tags}")
             }
-            _ => panic!("Scroll can't handle this message. Message:{:?}", message),
-        }
+        };
+
+        let inner_area = block.inner(area);
+        let editor_rows = self.editor.lines(self.editor_width()).len().clamp(1, 3);
+        let [editor_area, _, hint_area] = Layout::vertical([
+            Constraint::Length(editor_rows as u16),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+        .areas(inner_area);
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+        frame.render_widget(
+            Paragraph::new(hint).wrap(ratatui::widgets::Wrap { trim: false }),
+            hint_area,
+        );
+        self.render_editor(frame, editor_area);
     }
 
-    fn handle_expand(&mut self) {
-        if self.display.len() == 0 {
-            return;
+    /// The popup text is edited in while in `state`.
+    fn editor_popup_area(frame_area: Rect, state: &UIState) -> Rect {
+        match state {
+            UIState::SearchView | UIState::NotesView => Self::popup_area(frame_area, 70, 60),
+            UIState::PromptView(_) => Self::popup_area(frame_area, 60, 25),
+            _ => Self::popup_area(frame_area, 60, 20),
         }
+    }
 
-        let current_item = self.display.get_mut(self.selected_list_index).unwrap();
+    /// The columns the text being edited wraps at: the popup without its
+    /// borders, keeping a column for the cursor after the last character.
+    fn editor_width(&self) -> usize {
+        let (columns, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+        let area = Self::editor_popup_area(Rect::new(0, 0, columns, rows), &self.state);
+        usize::from(area.width.saturating_sub(3))
+    }
 
-        if current_item.expanded == false {
-            self.expanded_items.insert(current_item.id_path.clone());
-            let mut list_item_children = self.list.get_children(
-                self.list
-                    .get_list_item(current_item.id_path.last().unwrap())
-                    .unwrap(),
-            );
+    /// Draws the text being edited into `area`, scrolled so the cursor is in
+    /// view.
+    fn render_editor(&self, frame: &mut Frame, area: Rect) {
+        let width = self.editor_width();
+        let (row, column) = self.editor.cursor_position(width);
+        let offset = (row + 1).saturating_sub(area.height.into());
 
-            list_item_children.reverse();
+        let lines: Vec<Line> = self.editor.lines(width).into_iter().skip(offset).collect();
+        frame.render_widget(Paragraph::new(lines), area);
 
-            current_item.expanded = true;
+        frame.set_cursor_position(Position::new(
+            area.x + u16::try_from(column.min(width)).unwrap_or(0),
+            area.y + u16::try_from(row - offset).unwrap_or(0),
+        ));
+    }
 
-            let current_item_path = current_item.id_path.clone();
+    const TAG_COLORS: [Color; 6] = [
+        Color::Cyan,
+        Color::Magenta,
+        Color::LightBlue,
+        Color::LightMagenta,
+        Color::LightCyan,
+        Color::LightGreen,
+    ];
+
+    /// Splits a line of an item's text into spans, giving each of the item's
+    /// tags its own colour. The same tag always gets the same colour.
+    fn color_tags(line: &str, list_item: &just_lists_core::list_item::ListItem) -> Line<'static> {
+        let spans: Vec<Span> = line
+            .split_inclusive(' ')
+            .map(|word| {
+                let tag = word.trim_end().strip_prefix('#');
+
+                match tag.filter(|tag| list_item.has_tag(tag)) {
+                    Some(tag) => {
+                        let color_index = tag.to_lowercase().bytes().fold(0usize, |hash, b| {
+                            hash.wrapping_mul(31).wrapping_add(b.into())
+                        });
+                        Span::styled(
+                            word.to_string(),
+                            Style::new().fg(Self::TAG_COLORS[color_index % Self::TAG_COLORS.len()]),
+                        )
+                    }
+                    None => Span::raw(word.to_string()),
+                }
+            })
+            .collect();
 
-            for child in list_item_children {
-                let mut child_path = current_item_path.clone();
-                child_path.push(child.id.clone());
-                self.display.insert(
-                    self.selected_list_index + 1,
-                    ListEntry {
-                        id_path: child_path,
-                        expanded: false,
-                    },
-                );
-            }
-        } else {
-            self.expanded_items.retain(|p| *p != current_item.id_path);
-            current_item.expanded = false;
-            let current_path_length = current_item.id_path.len();
+        Line::from(spans)
+    }
 
-            let mut next_cursor_index = self.selected_list_index + 1;
+    fn today() -> NaiveDate {
+        chrono::Local::now().date_naive()
+    }
 
-            while next_cursor_index != self.display.len()
-                && current_path_length < self.display.get(next_cursor_index).unwrap().id_path.len()
-            {
-                next_cursor_index += 1;
-            }
+    /// The glyph, colour and selected background of `state`, as configured.
+    fn state_style(&self, state: &State) -> (&str, Color, Color) {
+        let Some(definition) = self.config.workflow.definition(state) else {
+            return (
+                Self::UNKNOWN_STATE_GLYPH,
+                Self::UNKNOWN_STATE_COLOR,
+                Self::UNKNOWN_STATE_COLOR,
+            );
+        };
 
-            for i in (self.selected_list_index + 1..next_cursor_index).rev() {
-                self.display.remove(i);
-            }
-        }
+        let color = definition
+            .color
+            .parse()
+            .unwrap_or(Self::UNKNOWN_STATE_COLOR);
+        let highlight = definition
+            .highlight
+            .as_ref()
+            .and_then(|highlight| highlight.parse().ok())
+            .unwrap_or(color);
+
+        (&definition.glyph, color, highlight)
     }
 
-    fn popup_area(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
-        let vertical = Layout::vertical([Constraint::Percentage(percent_y)]).flex(Flex::Center);
-        let horizontal = Layout::horizontal([Constraint::Percentage(percent_x)]).flex(Flex::Center);
-        let [area] = vertical.areas(area);
-        let [area] = horizontal.areas(area);
-        area
-    }
+    /// The due and scheduled dates shown after an item's text.
+    fn dates_text(list_item: &just_lists_core::list_item::ListItem, today: NaiveDate) -> String {
+        let format_date = |date: NaiveDate| match (date - today).num_days() {
+            0 => "today".to_string(),
+            1 => "tomorrow".to_string(),
+            -1 => "yesterday".to_string(),
+            _ => date.format("%Y-%m-%d").to_string(),
+        };
 
-    fn get_parent_from_path(path: &Vec<String>) -> Option<&str> {
-        if path.len() <= 1 {
-            None
-        } else {
-            Some(path.get(path.len() - 2).unwrap())
+        let mut dates = Vec::new();
+
+        if let Some(due) = list_item.due {
+            dates.push(format!("due {}", format_date(due)));
         }
-    }
 
-    fn open_or_create_file(path: &std::path::PathBuf) -> String {
-        let mut file: fs::File;
+        if let Some(scheduled) = list_item.scheduled {
+            dates.push(format!("scheduled {}", format_date(scheduled)));
+        }
 
-        if fs::exists(path).is_ok_and(|f| f) {
-            file = fs::File::open(path).unwrap();
+        if dates.is_empty() {
+            "".to_string()
         } else {
-            file = fs::File::create(path).unwrap();
+            format!(" ({})", dates.join(", "))
         }
+    }
 
-        let mut content: String = String::new();
-        let _ = file.read_to_string(&mut content);
+    /// A reminder of open items that are overdue or due today, if any.
+    fn due_reminder(&self) -> Option<String> {
+        let today = App::today();
+        let mut items: Vec<&just_lists_core::list_item::ListItem> =
+            self.list.get_top_level_list_items();
+        let mut seen = HashSet::new();
+        let (mut overdue, mut due_today) = (0, 0);
+
+        while let Some(item) = items.pop() {
+            if !seen.insert(&item.id) {
+                continue;
+            }
 
-        content
-    }
+            if item.is_overdue(today, &self.config.workflow) {
+                overdue += 1;
+            } else if item.is_due_today(today, &self.config.workflow) {
+                due_today += 1;
+            }
 
-    fn save_list(&self) {
-        if self.file_path.is_none() {
-            return;
+            items.extend(self.list.get_children(item));
         }
 
-        _ = fs::write(self.file_path.clone().unwrap(), self.list.into_string());
+        match (overdue, due_today) {
+            (0, 0) => None,
+            (overdue, 0) => Some(format!("{overdue} overdue (f to filter)")),
+            (0, due_today) => Some(format!("{due_today} due today (f to filter)")),
+            (overdue, due_today) => Some(format!(
+                "{overdue} overdue, {due_today} due today (f to filter)"
+            )),
+        }
     }
 
-    fn delete_selected_item(&mut self) {
-        if self.display.len() == 0 {
-            return;
-        }
+    fn render_search_popup(&self, frame: &mut Frame) {
+        let block = Block::new()
+            .title("[Search]")
+            .title_style(Style::default().add_modifier(Modifier::BOLD))
+            .title_bottom("[Enter] Jump to item [↑↓] Choose [Esc] Cancel")
+            .border_type(Self::BASE_UI_BORDER_TYPE)
+            .border_style(Self::BASE_UI_COLOR)
+            .borders(Borders::ALL);
+        let area = Self::editor_popup_area(frame.area(), &UIState::SearchView);
+        let inner_area = block.inner(area);
 
-        let item_to_delete_id_path = self
-            .display
-            .get(self.selected_list_index)
-            .unwrap()
-            .id_path
-            .clone();
-        let item_to_delete_id = item_to_delete_id_path.last().unwrap();
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
 
-        let selected_item = self.get_current_display_item();
+        let [query_area, _, results_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+        .areas(inner_area);
 
-        if let Some(item) = selected_item {
-            if item.expanded {
-                self.handle_expand();
+        let results: Vec<ListItem> = self
+            .search_results
+            .iter()
+            .map(|result| {
+                let values = self.path_values(&result.id_path);
+                let (value, ancestors) = values.split_last().unwrap_or((&"", &[]));
+
+                let mut line = Line::from(value.to_string());
+                if result.in_notes {
+                    line.push_span(Span::from(" ✎").style(Style::new().fg(Color::DarkGray)));
+                }
+                if !ancestors.is_empty() {
+                    line.push_span(
+                        Span::from(format!("  {}", ancestors.join(" › ")))
+                            .style(Style::new().fg(Color::DarkGray)),
+                    );
+                }
+
+                ListItem::from(line)
+            })
+            .collect();
+
+        let results_count = results.len();
+        let mut results_state = ListState::default();
+        results_state.select(Some(self.selected_search_result));
+
+        let results_list = if results_count == 0 && !self.editor.text().trim().is_empty() {
+            WidgetList::new(vec![ListItem::from("No matching items")])
+        } else {
+            WidgetList::new(results)
+                .highlight_style(Style::new().bg(Self::BASE_UI_COLOR).fg(Color::Black))
+        };
+
+        frame.render_stateful_widget(results_list, results_area, &mut results_state);
+        self.render_editor(frame, query_area);
+    }
+
+    fn render_archive_popup(&self, frame: &mut Frame) {
+        let block = Block::new()
+            .title("[Archive]")
+            .title_style(Style::default().add_modifier(Modifier::BOLD))
+            .title_bottom("[Enter] Restore [↑↓] Choose [Esc] Close")
+            .border_type(Self::BASE_UI_BORDER_TYPE)
+            .border_style(Self::BASE_UI_COLOR)
+            .borders(Borders::ALL);
+        let area = Self::popup_area(frame.area(), 70, 60);
+
+        let entries: Vec<ListItem> = self
+            .list
+            .archived_items()
+            .iter()
+            .rev()
+            .map(|entry| {
+                let (glyph, color, _) = self.state_style(&entry.item.state);
+                let mut details = format!(
+                    "  archived {}",
+                    entry
+                        .archived_at
+                        .with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M")
+                );
+
+                if !entry.descendants.is_empty() {
+                    details.push_str(&format!(", {} below it", entry.descendants.len()));
+                }
+
+                if let Some(parent) = entry
+                    .parent_id
+                    .as_deref()
+                    .and_then(|parent_id| self.list.get_list_item(parent_id))
+                {
+                    details.push_str(&format!(", from {}", parent.value));
+                }
+
+                ListItem::from(Line::from(vec![
+                    Span::from(format!("{glyph} {}", entry.item.value)).style(color),
+                    Span::from(details).style(Style::new().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect();
+
+        let entries_list = if entries.is_empty() {
+            WidgetList::new(vec![ListItem::from(
+                "Nothing archived yet. Press [a] on a completed item to archive it.",
+            )])
+        } else {
+            WidgetList::new(entries)
+                .highlight_style(Style::new().bg(Self::BASE_UI_COLOR).fg(Color::Black))
+        }
+        .block(block);
+
+        let mut entries_state = ListState::default();
+        entries_state.select(Some(self.selected_archive_index));
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(entries_list, area, &mut entries_state);
+    }
+
+    fn render_locations_popup(&self, frame: &mut Frame) {
+        let block = Block::new()
+            .title(match self.locations.len() {
+                1 => "[Appears in 1 place]".to_string(),
+                count => format!("[Appears in {count} places]"),
+            })
+            .title_style(Style::default().add_modifier(Modifier::BOLD))
+            .title_bottom("[Enter] Go there [↑↓] Choose [Esc] Close")
+            .border_type(Self::BASE_UI_BORDER_TYPE)
+            .border_style(Self::BASE_UI_COLOR)
+            .borders(Borders::ALL);
+        let area = Self::popup_area(frame.area(), 70, 40);
+
+        let current_path = self.get_current_display_item().map(|e| &e.id_path);
+
+        let locations: Vec<ListItem> = self
+            .locations
+            .iter()
+            .map(|id_path| {
+                let marker = if Some(id_path) == current_path {
+                    "• "
+                } else {
+                    "  "
+                };
+
+                ListItem::from(format!("{marker}{}", self.path_values(id_path).join(" › ")))
+            })
+            .collect();
+
+        let mut locations_state = ListState::default();
+        locations_state.select(Some(self.selected_location));
+
+        let locations_list = WidgetList::new(locations)
+            .highlight_style(Style::new().bg(Self::BASE_UI_COLOR).fg(Color::Black))
+            .block(block);
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(locations_list, area, &mut locations_state);
+    }
+
+    /// The values of the items along `id_path`.
+    fn path_values(&self, id_path: &[String]) -> Vec<&str> {
+        id_path
+            .iter()
+            .filter_map(|id| self.list.get_list_item(id))
+            .map(|item| item.value.as_str())
+            .collect()
+    }
+
+    fn render_paste_popup(&self, frame: &mut Frame) {
+        let block = Block::new()
+            .title("[Paste]")
+            .title_style(Style::default().add_modifier(Modifier::BOLD))
+            .title_bottom("[Esc] Cancel")
+            .border_type(Self::BASE_UI_BORDER_TYPE)
+            .border_style(Self::BASE_UI_COLOR)
+            .borders(Borders::ALL);
+        let area = Self::popup_area(frame.area(), 60, 40);
+
+        let value = self
+            .clipboard
+            .as_ref()
+            .and_then(|clipboard| self.list.get_list_item(&clipboard.list_item_id))
+            .map(|item| item.value.as_str())
+            .unwrap_or_default();
+
+        let text = format!(
+            "Paste \"{value}\"\n\n\
+            [c] as the last child of the selected item\n\
+            [b] before the selected item\n\
+            [a] after the selected item\n\
+            [t] at the top level\n\n\
+            Lowercase keys paste the item itself, so changes show up everywhere \
+            it appears. Uppercase keys (C, B, A, T) paste an independent copy."
+        );
+
+        let paste_content = Paragraph::new(text)
+            .wrap(ratatui::widgets::Wrap { trim: false })
+            .block(block);
+        frame.render_widget(Clear, area);
+        frame.render_widget(paste_content, area);
+    }
+
+    fn render_conflict_popup(&self, frame: &mut Frame) {
+        let block = Block::new()
+            .title("List changed on disk")
+            .border_type(Self::BASE_UI_BORDER_TYPE)
+            .border_style(Color::Yellow)
+            .borders(Borders::ALL);
+        let area = Self::popup_area(frame.area(), 60, 40);
+
+        let text = "The list file was changed by another program since it was opened, \
+            so your last change was not saved.\n\n\
+            [r] Reload (discard your unsaved changes)\n\
+            [o] Overwrite (discard the other changes)\n\
+            [m] Merge both sets of changes\n\
+            [Esc] Decide later (Ctrl-s to try again)";
+
+        let conflict_content = Paragraph::new(text)
+            .wrap(ratatui::widgets::Wrap { trim: false })
+            .block(block);
+        frame.render_widget(Clear, area);
+        frame.render_widget(conflict_content, area);
+    }
+
+    fn render_recovery_popup(&self, frame: &mut Frame) {
+        let block = Block::new()
+            .title("Unable to load list")
+            .border_type(Self::BASE_UI_BORDER_TYPE)
+            .border_style(Color::Red)
+            .borders(Borders::ALL);
+        let area = Self::popup_area(frame.area(), 60, 40);
+
+        let backup_option = match self.backup_path() {
+            Some(backup_path) if backup_path.exists() => {
+                format!("[b] Open backup ({})", backup_path.display())
+            }
+            _ => "[b] Open backup (no backup found)".to_string(),
+        };
+
+        let text = format!(
+            "{}\n\n{}\n[r] Start read-only with what can be read (changes will not be saved)\n[Esc] Quit",
+            self.load_error.clone().unwrap_or_default(),
+            backup_option,
+        );
+
+        let recovery_content = Paragraph::new(text)
+            .wrap(ratatui::widgets::Wrap { trim: false })
+            .block(block);
+        frame.render_widget(Clear, area);
+        frame.render_widget(recovery_content, area);
+    }
+
+    fn handle_event(&mut self) -> color_eyre::Result<Option<Message>> {
+        if event::poll(Duration::from_millis(250))? {
+            match event::read()? {
+                Event::Key(key) if key.kind == event::KeyEventKind::Press => {
+                    return Ok(self.handle_key(key));
+                }
+                Event::Paste(text) if self.is_editing() => {
+                    return Ok(Some(Message::PasteText(text)));
+                }
+                _ => (),
+            }
+        }
+        Ok(None)
+    }
+
+    fn is_editing(&self) -> bool {
+        matches!(
+            self.state,
+            UIState::EditView | UIState::NotesView | UIState::PromptView(_) | UIState::SearchView
+        )
+    }
+
+    fn handle_key(&mut self, key: event::KeyEvent) -> Option<Message> {
+        let focused = self
+            .display_parent_item
+            .as_ref()
+            .is_some_and(|path| !path.is_empty());
+
+        if matches!(self.state, UIState::ListView) {
+            return match self.keys.read(&self.config.keymap, &key) {
+                Some(Message::Esc) if focused => Some(Message::FocusOnParentItem),
+                message => message,
+            };
+        }
+
+        if self.is_editing() {
+            let new_line = key
+                .modifiers
+                .intersects(KeyModifiers::SHIFT | KeyModifiers::ALT);
+            // Notes often run over several paragraphs, so Enter starts a
+            // new line there and Ctrl-s saves.
+            let notes = matches!(self.state, UIState::NotesView);
+
+            return match key.code {
+                KeyCode::Esc => Some(Message::Esc),
+                KeyCode::Char('s') if notes && key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(Message::Enter)
+                }
+                KeyCode::Enter if !new_line && !notes => Some(Message::Enter),
+                KeyCode::Up if matches!(self.state, UIState::SearchView) => Some(Message::Up),
+                KeyCode::Down if matches!(self.state, UIState::SearchView) => Some(Message::Down),
+                _ => Some(Message::EditorKey(key)),
+            };
+        }
+
+        match key.code {
+            KeyCode::Esc if focused => Some(Message::FocusOnParentItem),
+            KeyCode::Esc => Some(Message::Esc),
+            KeyCode::Enter => Some(Message::Enter),
+            KeyCode::Up => Some(Message::Up),
+            KeyCode::Down => Some(Message::Down),
+            KeyCode::Char(c) => match self.state {
+                UIState::ListView
+                | UIState::EditView
+                | UIState::NotesView
+                | UIState::PromptView(_)
+                | UIState::SearchView
+                | UIState::ArchiveView
+                | UIState::LocationsView => None,
+                UIState::PasteView => Some(Message::Text(c)),
+                UIState::RecoveryView => match key.code {
+                    KeyCode::Char('b') => Some(Message::OpenBackup),
+                    KeyCode::Char('r') => Some(Message::StartReadOnly),
+                    _ => None,
+                },
+                UIState::ConflictView => match key.code {
+                    KeyCode::Char('r') => Some(Message::Reload),
+                    KeyCode::Char('o') => Some(Message::Overwrite),
+                    KeyCode::Char('m') => Some(Message::Merge),
+                    _ => None,
+                },
+            },
+
+            _ => None,
+        }
+    }
+
+    /// Handles a message of the list view, as many times as the count typed
+    /// before it asks for.
+    fn handle_list_message(&mut self, message: Message, count: Option<usize>) {
+        match message {
+            Message::First => return self.select_line(count.map_or(0, |line| line - 1)),
+            Message::Last => {
+                return self.select_line(count.map_or(usize::MAX, |line| line - 1));
+            }
+            _ => (),
+        }
+
+        let count = count.unwrap_or(1);
+
+        // A change repeated by a count is undone in one go and saved once.
+        let grouped = count > 1 && message.is_change();
+        if grouped {
+            self.history.begin_group();
+            self.defer_saves = true;
+        }
+
+        for _ in 0..count {
+            self.apply_list_message(message.clone());
+
+            // Stop once a popup or the editor opens.
+            if !matches!(self.state, UIState::ListView) {
+                break;
+            }
+        }
+
+        if grouped {
+            self.history.end_group();
+            self.defer_saves = false;
+            self.save_list();
+        }
+
+        if message.is_change() {
+            self.last_change = Some((message, count));
+        }
+    }
+
+    /// Repeats the last change, as many times as before unless a new count
+    /// was typed.
+    fn repeat_last_change(&mut self) {
+        let Some((message, count)) = self.last_change.clone() else {
+            self.status_message = Some("Nothing to repeat".to_string());
+            return;
+        };
+
+        let count = self.keys.take_count().unwrap_or(count);
+        self.handle_list_message(message, Some(count));
+    }
+
+    fn apply_list_message(&mut self, message: Message) {
+        match message {
+            Message::Down => self.handle_scroll(Message::Down),
+            Message::Up => self.handle_scroll(Message::Up),
+            Message::Enter => self.handle_expand(),
+            Message::FocusOnCurrentItem => self.focus_on_current(),
+            Message::FocusOnParentItem => self.focus_on_parent(),
+            Message::Copy => self.copy(),
+            Message::Cut => self.cut(),
+            Message::Paste => self.paste(PasteTarget::Child, false),
+            Message::PasteMenu => self.open_paste_menu(),
+            Message::ShowLocations => self.show_locations(),
+            Message::Unshare => self.unshare_selected_item(),
+            Message::Save => self.retry_save(),
+            Message::Undo => self.undo(),
+            Message::Redo => self.redo(),
+            Message::Delete => self.delete_selected_item(false),
+            Message::DeleteEverywhere => self.delete_selected_item(true),
+            Message::New => self.add_new_list_item(),
+            Message::InsertChild => self.insert_child_item(),
+            Message::Space => self.toggle_item_completion(),
+            Message::SetDueDate => self.open_prompt(Prompt::DueDate),
+            Message::SetScheduledDate => self.open_prompt(Prompt::ScheduledDate),
+            Message::EditTags => self.open_prompt(Prompt::Tags),
+            Message::FilterByTag => self.open_prompt(Prompt::TagFilter),
+            Message::RaisePriority => self.change_priority(Priority::raised),
+            Message::LowerPriority => self.change_priority(Priority::lowered),
+            Message::CycleSort => self.cycle_sort(),
+            Message::CycleDueFilter => self.cycle_due_filter(),
+            Message::Search => self.open_search(),
+            Message::ToggleHideCompleted => self.toggle_hide_completed(),
+            Message::ToggleBlockedOnly => self.toggle_blocked_only(),
+            Message::Archive => self.archive_selected_item(),
+            Message::OpenArchive => self.open_archive(),
+            Message::MoveUp => self.move_selected_item(false),
+            Message::MoveDown => self.move_selected_item(true),
+            Message::Indent => self.indent_selected_item(),
+            Message::Outdent => self.outdent_selected_item(),
+            Message::Edit => self.toggle_edit_mode(),
+            Message::EditNotes => self.edit_notes(),
+            Message::ToggleNotes => self.toggle_notes(),
+            Message::PreviousSibling => self.select_sibling(false),
+            Message::NextSibling => self.select_sibling(true),
+            _ => (),
+        }
+    }
+
+    fn handle_scroll(&mut self, message: Message) {
+        if self.display.is_empty() {
+            return;
+        }
+
+        match message {
+            Message::Down => {
+                if self.selected_list_index == self.display.len() - 1 {
+                    self.selected_list_index = 0;
+                } else {
+                    self.selected_list_index += 1;
+                }
+            }
+            Message::Up => {
+                if self.selected_list_index == 0 {
+                    self.selected_list_index = self.display.len() - 1;
+                } else {
+                    self.selected_list_index -= 1;
+                }
+            }
+            _ => panic!("Scroll can't handle this message. Message:{:?}", message),
+        }
+    }
+
+    /// Selects the entry at `index`, or the last one if there are fewer.
+    fn select_line(&mut self, index: usize) {
+        self.selected_list_index = index.min(self.display.len().saturating_sub(1));
+    }
+
+    /// Selects the next or previous entry with the same parent, if any.
+    fn select_sibling(&mut self, forwards: bool) {
+        let Some(sibling) = self.sibling_entry(self.selected_list_index, forwards) else {
+            return;
+        };
+
+        if let Some(index) = self
+            .display
+            .iter()
+            .position(|entry| entry.id_path == sibling.id_path)
+        {
+            self.selected_list_index = index;
+        }
+    }
+
+    fn handle_expand(&mut self) {
+        if self.display.is_empty() {
+            return;
+        }
+
+        let current_item = self.display.get(self.selected_list_index).unwrap();
+
+        if !current_item.expanded {
+            self.expanded_items.insert(current_item.id_path.clone());
+            self.expand_entry(self.selected_list_index);
+            self.update_progress();
+        } else {
+            self.expanded_items.retain(|p| *p != current_item.id_path);
+            self.collapse_entry(self.selected_list_index);
+        }
+    }
+
+    /// Shows the children of the display entry at `index` below it.
+    fn expand_entry(&mut self, index: usize) {
+        let current_item = self.display.get_mut(index).unwrap();
+        current_item.expanded = true;
+
+        let current_item_path = current_item.id_path.clone();
+        let list_item = self
+            .list
+            .get_list_item(current_item_path.last().unwrap())
+            .unwrap();
+
+        let mut list_item_children = self.view.visible(
+            &self.list,
+            self.list.get_children(list_item),
+            App::today(),
+            &self.config.workflow,
+        );
+
+        list_item_children.reverse();
+
+        for child in list_item_children {
+            let mut child_path = current_item_path.clone();
+            child_path.push(child.id.clone());
+            self.display.insert(
+                index + 1,
+                ListEntry {
+                    id_path: child_path,
+                    expanded: false,
+                },
+            );
+        }
+    }
+
+    fn collapse_entry(&mut self, index: usize) {
+        let current_item = self.display.get_mut(index).unwrap();
+        current_item.expanded = false;
+        let current_path_length = current_item.id_path.len();
+
+        let mut next_cursor_index = index + 1;
+
+        while next_cursor_index != self.display.len()
+            && current_path_length < self.display.get(next_cursor_index).unwrap().id_path.len()
+        {
+            next_cursor_index += 1;
+        }
+
+        for i in (index + 1..next_cursor_index).rev() {
+            self.display.remove(i);
+        }
+    }
+
+    fn visible_children<'a>(
+        &'a self,
+        list_item: &'a just_lists_core::list_item::ListItem,
+        today: NaiveDate,
+    ) -> Vec<&'a just_lists_core::list_item::ListItem> {
+        self.view.visible(
+            &self.list,
+            self.list.get_children(list_item),
+            today,
+            &self.config.workflow,
+        )
+    }
+
+    fn popup_area(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+        let vertical = Layout::vertical([Constraint::Percentage(percent_y)]).flex(Flex::Center);
+        let horizontal = Layout::horizontal([Constraint::Percentage(percent_x)]).flex(Flex::Center);
+        let [area] = vertical.areas(area);
+        let [area] = horizontal.areas(area);
+        area
+    }
+
+    fn get_parent_from_path(path: &[String]) -> Option<&str> {
+        if path.len() <= 1 {
+            None
+        } else {
+            Some(path.get(path.len() - 2).unwrap())
+        }
+    }
+
+    fn backup_path(&self) -> Option<PathBuf> {
+        self.storage.as_ref().map(|storage| storage.backup_path(0))
+    }
+
+    fn open_backup(&mut self) {
+        let Some(storage) = self.storage.as_mut() else {
+            return;
+        };
+
+        if !storage.backup_path(0).exists() {
+            return;
+        }
+
+        match storage.load_backup(0) {
+            std::result::Result::Ok(list) => {
+                self.list = list;
+                self.load_error = None;
+                self.state = UIState::ListView;
+                self.update_display(None);
+            }
+            Err(error) => {
+                self.load_error = Some(format!(
+                    "{}\n\nThe backup could not be loaded either: {}",
+                    self.load_error.clone().unwrap_or_default(),
+                    error
+                ));
+            }
+        }
+    }
+
+    /// Shows what can still be read from the damaged file. When its JSON is
+    /// intact the list is repaired in memory; otherwise it starts out empty.
+    fn start_read_only(&mut self) {
+        self.read_only = true;
+        self.state = UIState::ListView;
+
+        let salvaged = self
+            .storage
+            .as_ref()
+            .and_then(|storage| fs::read_to_string(storage.path()).ok())
+            .and_then(|content| List::try_from_str_unchecked(&content).ok());
+
+        match salvaged {
+            Some(mut list) => {
+                list.repair();
+                self.list = list;
+                self.status_message = Some("Showing what could be read from the file".to_string());
+            }
+            None => {
+                self.status_message = Some("Nothing could be read from the file".to_string());
+            }
+        }
+
+        self.update_display(None);
+    }
+
+    fn save_list(&mut self) {
+        if self.defer_saves {
+            return;
+        }
+
+        self.write_list(false);
+    }
+
+    /// Saves the list (and the undo history when it is kept). A failure is
+    /// kept in the status bar until a later save succeeds.
+    ///
+    /// Unless `overwrite` is set, the save is refused if the file was changed
+    /// by another program, and the conflict dialog is shown.
+    fn write_list(&mut self, overwrite: bool) {
+        let Some(storage) = &mut self.storage else {
+            return;
+        };
+
+        if self.read_only {
+            return;
+        }
+
+        let result = if overwrite {
+            storage.overwrite(&self.list)
+        } else {
+            storage.save(&self.list)
+        };
+
+        self.save_conflict = matches!(result, Err(StorageError::Conflict));
+
+        let mut result = result.map_err(|e| e.to_string());
+
+        if result.is_ok()
+            && self.keep_history
+            && let Some(storage) = &self.storage
+        {
+            result = storage
+                .save_history(&self.history)
+                .map_err(|e| format!("Unable to save the undo history: {e}"));
+        }
+
+        self.save_error = result.err();
+    }
+
+    fn retry_save(&mut self) {
+        if self.storage.is_none() || self.read_only {
+            return;
+        }
+
+        self.save_list();
+
+        if self.save_error.is_none() {
+            self.status_message = Some("Saved".to_string());
+        }
+    }
+
+    fn reload_from_disk(&mut self) {
+        let Some(storage) = &mut self.storage else {
+            return;
+        };
+
+        match storage.load() {
+            std::result::Result::Ok(list) => {
+                self.list = list;
+                self.save_conflict = false;
+                self.save_error = None;
+                self.state = UIState::ListView;
+                self.status_message = Some("Reloaded the list from disk".to_string());
+                self.forget_history();
+                self.update_display(None);
+            }
+            Err(error) => self.status_message = Some(format!("Unable to reload: {error}")),
+        }
+    }
+
+    /// Picks up changes other programs made to the file. If there are
+    /// changes here that could not be saved, the conflict dialog is shown
+    /// instead so they are not lost.
+    fn reload_if_changed_on_disk(&mut self) {
+        let Some(storage) = &mut self.storage else {
+            return;
+        };
+
+        if !storage.has_changed_on_disk().unwrap_or(false) {
+            return;
+        }
+
+        if self.save_error.is_some() && !self.read_only {
+            self.save_conflict = true;
+            return;
+        }
+
+        match storage.load() {
+            std::result::Result::Ok(list) => {
+                self.list = list;
+                self.status_message = Some("Reloaded after an outside change".to_string());
+                self.forget_history();
+                self.update_display(None);
+            }
+            Err(error) => {
+                self.status_message = Some(format!(
+                    "The list changed on disk but could not be reloaded: {error}"
+                ))
+            }
+        }
+    }
+
+    fn overwrite_file(&mut self) {
+        self.write_list(true);
+        self.state = UIState::ListView;
+    }
+
+    fn merge_with_file(&mut self) {
+        let Some(storage) = &mut self.storage else {
+            return;
+        };
+
+        match storage.merge(&self.list) {
+            std::result::Result::Ok(result) => {
+                self.list = result.list;
+                self.history = History::new();
+                self.state = UIState::ListView;
+                self.status_message = Some(if result.conflicts.is_empty() {
+                    "Merged the changes from disk".to_string()
+                } else {
+                    format!(
+                        "Merged the changes from disk, kept your version of {} conflicting change(s)",
+                        result.conflicts.len()
+                    )
+                });
+                self.save_list();
+                self.update_display(None);
+            }
+            Err(error) => self.status_message = Some(format!("Unable to merge: {error}")),
+        }
+    }
+
+    /// Starts a new undo history after the list was replaced, since the old
+    /// changes no longer apply to it.
+    fn forget_history(&mut self) {
+        self.history = History::new();
+
+        if self.keep_history
+            && let Some(storage) = &self.storage
+            && let Err(error) = storage.remove_history()
+        {
+            self.status_message = Some(format!("Unable to remove the undo history: {error}"));
+        }
+    }
+
+    fn load_history(&mut self) {
+        let Some(storage) = &self.storage else {
+            return;
+        };
+
+        match storage.load_history() {
+            std::result::Result::Ok(Some(history)) => self.history = history,
+            std::result::Result::Ok(None) => (),
+            Err(error) => {
+                self.status_message = Some(format!("Unable to load the undo history: {error}"))
+            }
+        }
+    }
+
+    /// Applies `command` to the list as a single undo step, reporting
+    /// failures in the status line. Returns whether the command was applied.
+    fn apply_command(&mut self, command: std::result::Result<Command, CommandError>) -> bool {
+        let result = command.and_then(|command| self.history.apply(&mut self.list, command));
+
+        match result {
+            std::result::Result::Ok(()) => true,
+            Err(error) => {
+                self.status_message = Some(error.to_string());
+                false
+            }
+        }
+    }
+
+    fn undo(&mut self) {
+        match self.history.undo(&mut self.list) {
+            std::result::Result::Ok(true) => {
+                self.update_display(None);
+                self.save_list();
+            }
+            std::result::Result::Ok(false) => {
+                self.status_message = Some("Nothing to undo".to_string())
+            }
+            Err(error) => self.status_message = Some(format!("Unable to undo: {error}")),
+        }
+    }
+
+    fn redo(&mut self) {
+        match self.history.redo(&mut self.list) {
+            std::result::Result::Ok(true) => {
+                self.update_display(None);
+                self.save_list();
+            }
+            std::result::Result::Ok(false) => {
+                self.status_message = Some("Nothing to redo".to_string())
+            }
+            Err(error) => self.status_message = Some(format!("Unable to redo: {error}")),
+        }
+    }
+
+    /// Removes the selected occurrence of an item. Shared items keep their
+    /// other occurrences unless `everywhere` is set.
+    fn delete_selected_item(&mut self, everywhere: bool) {
+        if self.display.is_empty() {
+            return;
+        }
+
+        let item_to_delete_id_path = self
+            .display
+            .get(self.selected_list_index)
+            .unwrap()
+            .id_path
+            .clone();
+        let item_to_delete_id = item_to_delete_id_path.last().unwrap();
+
+        let selected_item = self.get_current_display_item();
+
+        if let Some(item) = selected_item
+            && item.expanded
+        {
+            self.handle_expand();
+        }
+
+        self.display.remove(self.selected_list_index);
+
+        let parent = App::get_parent_from_path(&item_to_delete_id_path).map(|s| s.to_string());
+
+        if everywhere {
+            self.apply_command(Command::remove_everywhere(&self.list, item_to_delete_id));
+        } else {
+            self.apply_command(Command::unlink(
+                &self.list,
+                item_to_delete_id,
+                parent.as_deref(),
+            ));
+        }
+
+        if !self.display.is_empty() {
+            self.selected_list_index = self.selected_list_index.clamp(0, self.display.len() - 1);
+        }
+
+        self.update_display(None);
+        self.save_list();
+    }
+
+    fn add_new_list_item(&mut self) {
+        self.clear_filters();
+
+        let item = just_lists_core::list_item::ListItem::new("".to_string());
+        let mut current_item_path: Vec<String>;
+
+        if let Some(selected_list_entry) = self.get_current_display_item()
+            && selected_list_entry.expanded
+        {
+            self.handle_expand();
+        }
+
+        if self.display.is_empty() {
+            current_item_path = vec![item.id.clone()];
+
+            if !self.apply_command(Command::add_item(&self.list, item, None, None)) {
+                return;
+            }
+
+            self.display.insert(
+                0,
+                ListEntry {
+                    id_path: current_item_path.clone(),
+                    expanded: false,
+                },
+            )
+        } else {
+            current_item_path = self
+                .display
+                .get(self.selected_list_index)
+                .unwrap()
+                .id_path
+                .clone();
+
+            let selected_item = current_item_path.remove(current_item_path.len() - 1);
+            current_item_path.push(item.id.clone());
+
+            if current_item_path.len() == 1 {
+                let index_of_selection = match self.list.get_index_of_top_level_item(&selected_item)
+                {
+                    std::result::Result::Ok(index) => Some(index + 1),
+                    Err(_) => None,
+                };
+
+                if !self.apply_command(Command::add_item(
+                    &self.list,
+                    item,
+                    None,
+                    index_of_selection,
+                )) {
+                    return;
+                }
+            } else {
+                let parent_id = current_item_path[current_item_path.len() - 2].clone();
+                let index_of_child = match self.list.get_index_of_child(&selected_item, &parent_id)
+                {
+                    core::result::Result::Ok(index) => index,
+                    Err(_) => {
+                        return;
+                    }
+                };
+
+                if !self.apply_command(Command::add_item(
+                    &self.list,
+                    item,
+                    Some(&parent_id),
+                    Some(index_of_child + 1),
+                )) {
+                    return;
+                }
             }
+
+            self.display.insert(
+                self.selected_list_index + 1,
+                ListEntry {
+                    id_path: current_item_path.clone(),
+                    expanded: false,
+                },
+            )
         }
 
-        self.display.remove(self.selected_list_index);
+        self.update_display(Some(current_item_path));
+        self.toggle_edit_mode();
+        self.save_list();
+    }
+
+    fn insert_child_item(&mut self) {
+        if self.display.is_empty() {
+            return;
+        }
+
+        self.clear_filters();
+
+        let item = just_lists_core::list_item::ListItem::new("".to_string());
+
+        let parent_path = self
+            .display
+            .get(self.selected_list_index)
+            .unwrap()
+            .id_path
+            .clone();
+
+        let mut child_path = parent_path.clone();
+        child_path.push(item.id.clone());
+
+        if !self.apply_command(Command::add_item(
+            &self.list,
+            item,
+            Some(parent_path.last().unwrap()),
+            None,
+        )) {
+            return;
+        }
+
+        let current_item = self.display.get_mut(self.selected_list_index).unwrap();
+
+        if !current_item.expanded {
+            self.handle_expand();
+        } else {
+            self.handle_expand();
+            self.handle_expand();
+        }
+
+        self.update_display(Some(child_path));
+        self.toggle_edit_mode();
+
+        self.save_list();
+    }
+
+    fn toggle_item_completion(&mut self) {
+        if self.display.is_empty() {
+            return;
+        }
+
+        let list_entry = self.display.get(self.selected_list_index).unwrap();
+        let item_id = list_entry.id_path.last().unwrap().clone();
+
+        let next_state = self
+            .config
+            .workflow
+            .next(&self.list.get_list_item(&item_id).unwrap().state);
+        let config = &self.config;
+
+        let result = self.history.execute(&mut self.list, |transaction| {
+            transaction.apply(Command::edit(transaction.list(), &item_id, |list_item| {
+                list_item.state = next_state;
+            })?)?;
+
+            if config.auto_complete_parents {
+                transaction.complete_finished_ancestors(&item_id, &config.workflow)?;
+            }
+
+            std::result::Result::Ok(())
+        });
+
+        if let Err(error) = result {
+            self.status_message = Some(error.to_string());
+            return;
+        }
+
+        self.update_display(None);
+        self.save_list();
+    }
+
+    /// Moves the selected occurrence of a finished item, and everything
+    /// below it, into the list's archive.
+    fn archive_selected_item(&mut self) {
+        let Some(list_entry) = self.get_current_display_item() else {
+            return;
+        };
+        let id_path = list_entry.id_path.clone();
+        let item = self.list.get_list_item(id_path.last().unwrap()).unwrap();
+
+        if !self.config.workflow.is_done(&item.state) {
+            self.status_message = Some("Only completed items can be archived".to_string());
+            return;
+        }
+
+        let value = item.value.clone();
+        let parent = App::get_parent_from_path(&id_path).map(|s| s.to_string());
+        let command = Command::archive(&self.list, &item.id, parent.as_deref(), Utc::now());
+
+        if self.apply_command(command) {
+            self.status_message = Some(format!("Archived \"{value}\" (A to browse the archive)"));
+            self.update_display(None);
+            self.save_list();
+        }
+    }
+
+    fn open_archive(&mut self) {
+        self.selected_archive_index = 0;
+        self.state = UIState::ArchiveView;
+    }
+
+    fn restore_archived_item(&mut self) {
+        let archive = self.list.archived_items();
+        let Some(entry) = archive
+            .len()
+            .checked_sub(self.selected_archive_index + 1)
+            .map(|index| archive[index].clone())
+        else {
+            return;
+        };
+
+        self.state = UIState::ListView;
+
+        if self.apply_command(Command::restore(&self.list, &entry)) {
+            self.status_message = Some(format!("Restored \"{}\"", entry.item.value));
+            self.update_display(None);
+            self.save_list();
+        }
+    }
+
+    fn open_search(&mut self) {
+        self.editor = TextEditor::new("");
+        self.update_search();
+        self.state = UIState::SearchView;
+    }
+
+    fn update_search(&mut self) {
+        self.search_results = search(&self.list, self.editor.text());
+        self.selected_search_result = 0;
+    }
+
+    fn jump_to_search_result(&mut self) {
+        let Some(result) = self.search_results.get(self.selected_search_result) else {
+            return;
+        };
+
+        self.state = UIState::ListView;
+        self.jump_to(result.id_path.clone());
+    }
+
+    /// Shows the item at `id_path` in the list and selects it, leaving the
+    /// focused item and clearing filters if they would hide it.
+    fn jump_to(&mut self, id_path: Vec<String>) {
+        if self
+            .display_parent_item
+            .as_ref()
+            .is_some_and(|parent| parent.len() >= id_path.len() || !id_path.starts_with(parent))
+        {
+            self.display_parent_item = None;
+        }
+
+        self.clear_filters();
+
+        for length in 1..id_path.len() {
+            self.expanded_items.insert(id_path[..length].to_vec());
+        }
+
+        self.update_display(Some(id_path.clone()));
+
+        if self.view.hide_done
+            && self
+                .get_current_display_item()
+                .is_none_or(|entry| entry.id_path != id_path)
+        {
+            self.view.hide_done = false;
+            self.status_message = Some("Showing completed items to show the item".to_string());
+            self.update_display(Some(id_path));
+        }
+    }
+
+    fn open_prompt(&mut self, prompt: Prompt) {
+        let list_item = self.get_current_display_item().map(|list_entry| {
+            self.list
+                .get_list_item(list_entry.id_path.last().unwrap())
+                .unwrap()
+        });
+
+        if prompt.needs_item() && list_item.is_none() {
+            return;
+        }
+
+        let format_date = |date: Option<NaiveDate>| {
+            date.map(|date| date.format("%Y-%m-%d").to_string())
+                .unwrap_or_default()
+        };
+
+        let text = match (prompt, list_item) {
+            (Prompt::DueDate, Some(list_item)) => format_date(list_item.due),
+            (Prompt::ScheduledDate, Some(list_item)) => format_date(list_item.scheduled),
+            (Prompt::Tags, Some(list_item)) => list_item
+                .tags
+                .iter()
+                .map(|tag| format!("#{tag}"))
+                .collect::<Vec<String>>()
+                .join(" "),
+            (Prompt::TagFilter, _) => self.view.tag_filter.clone().unwrap_or_default(),
+            _ => "".to_string(),
+        };
+        self.editor = TextEditor::new(&text);
+        self.state = UIState::PromptView(prompt);
+    }
+
+    fn submit_prompt(&mut self, prompt: Prompt) {
+        let input = self.editor.text().trim().to_string();
+
+        let edit: Box<dyn FnOnce(&mut just_lists_core::list_item::ListItem)> = match prompt {
+            Prompt::DueDate | Prompt::ScheduledDate => {
+                let date = if input.is_empty() {
+                    None
+                } else {
+                    match parse_date(&input, App::today()) {
+                        Some(date) => Some(date),
+                        None => {
+                            self.status_message = Some(format!("Unrecognised date `{input}`"));
+                            return;
+                        }
+                    }
+                };
+
+                if prompt == Prompt::DueDate {
+                    Box::new(move |list_item| list_item.due = date)
+                } else {
+                    Box::new(move |list_item| list_item.scheduled = date)
+                }
+            }
+            Prompt::Tags => {
+                let tags = parse_tag_list(&input);
+                Box::new(move |list_item| list_item.tags = tags)
+            }
+            Prompt::TagFilter => {
+                self.state = UIState::ListView;
+                self.set_tag_filter(parse_tag_list(&input).into_iter().next());
+                return;
+            }
+        };
+
+        self.state = UIState::ListView;
+
+        let Some(list_entry) = self.get_current_display_item() else {
+            return;
+        };
+        let item_id = list_entry.id_path.last().unwrap().clone();
+
+        if self.apply_command(Command::edit(&self.list, &item_id, edit)) {
+            self.update_display(None);
+            self.save_list();
+        }
+    }
+
+    fn set_tag_filter(&mut self, tag: Option<String>) {
+        self.status_message = Some(match &tag {
+            Some(tag) => format!("Showing items tagged #{tag}"),
+            None => "Showing all tags".to_string(),
+        });
+        self.view.tag_filter = tag;
+        self.update_display(None);
+    }
+
+    fn change_priority(&mut self, change: fn(Priority) -> Priority) {
+        let Some(list_entry) = self.get_current_display_item() else {
+            return;
+        };
+        let item_id = list_entry.id_path.last().unwrap().clone();
+
+        let Some(list_item) = self.list.get_list_item(&item_id) else {
+            return;
+        };
+
+        let priority = change(list_item.priority);
+
+        if priority == list_item.priority {
+            return;
+        }
+
+        let command = Command::edit(&self.list, &item_id, |list_item| {
+            list_item.priority = priority
+        });
+
+        if self.apply_command(command) {
+            self.update_display(None);
+            self.save_list();
+        }
+    }
+
+    fn cycle_sort(&mut self) {
+        self.view.sort = self.view.sort.next();
+        self.status_message = Some(format!("Sorted by {}", self.view.sort.label()));
+        self.update_display(None);
+    }
+
+    fn cycle_due_filter(&mut self) {
+        self.view.due_filter = self.view.due_filter.next();
+        self.status_message = Some(format!("Showing {}", self.view.due_filter.label()));
+        self.update_display(None);
+    }
+
+    fn toggle_hide_completed(&mut self) {
+        self.view.hide_done = !self.view.hide_done;
+        self.status_message = Some(if self.view.hide_done {
+            "Hiding completed items".to_string()
+        } else {
+            "Showing completed items".to_string()
+        });
+        self.update_display(None);
+    }
+
+    fn toggle_notes(&mut self) {
+        self.show_notes = !self.show_notes;
+        self.status_message = Some(if self.show_notes {
+            "Showing notes".to_string()
+        } else {
+            "Hiding notes".to_string()
+        });
+    }
+
+    fn toggle_blocked_only(&mut self) {
+        self.view.state_filter = match self.view.state_filter {
+            Some(State::Blocked) => None,
+            _ => Some(State::Blocked),
+        };
+        self.status_message = Some(match self.view.state_filter {
+            Some(_) => "Showing only blocked items".to_string(),
+            None => "Showing all items".to_string(),
+        });
+        self.update_display(None);
+    }
+
+    /// New items would be hidden straight away by most filters, so they are
+    /// cleared before adding one.
+    fn clear_filters(&mut self) {
+        if self.view.is_filtering() {
+            self.view = ViewOptions {
+                sort: self.view.sort,
+                hide_done: self.view.hide_done,
+                ..ViewOptions::new()
+            };
+            self.status_message = Some("Filter cleared to show the item".to_string());
+            self.update_display(None);
+        }
+    }
+
+    fn toggle_edit_mode(&mut self) {
+        match self.state {
+            UIState::ListView => {
+                if self.display.is_empty() {
+                    return;
+                }
+
+                let list_entry = self.display.get(self.selected_list_index).unwrap();
+                let list_item = self
+                    .list
+                    .get_list_item(list_entry.id_path.last().unwrap())
+                    .unwrap();
+                self.editor = TextEditor::multiline(&list_item.value);
+                self.state = UIState::EditView
+            }
+            UIState::EditView => self.state = UIState::ListView,
+            UIState::NotesView
+            | UIState::RecoveryView
+            | UIState::ConflictView
+            | UIState::PromptView(_)
+            | UIState::SearchView
+            | UIState::ArchiveView
+            | UIState::PasteView
+            | UIState::LocationsView => (),
+        }
+    }
+
+    fn save_edited_text(&mut self) {
+        self.state = UIState::ListView;
+
+        let Some(list_entry) = self.get_current_display_item() else {
+            return;
+        };
+        let item_id = list_entry.id_path.last().unwrap().clone();
+        let text = self.editor.text().to_string();
+
+        self.set_item_text(&item_id, &text);
+    }
+
+    fn set_item_text(&mut self, item_id: &str, text: &str) {
+        // `#tag` words typed into the text are moved to the item's tags.
+        let (value, new_tags) = extract_tags(text);
+
+        if self
+            .list
+            .get_list_item(item_id)
+            .is_some_and(|item| item.value == value && new_tags.iter().all(|tag| item.has_tag(tag)))
+        {
+            return;
+        }
 
-        let parent = App::get_parent_from_path(&item_to_delete_id_path).map(|s| s.to_string());
+        let command = Command::edit(&self.list, item_id, |list_item| {
+            list_item.value = value;
 
-        _ = self
-            .list
-            .remove_child_list_item(item_to_delete_id, parent.as_ref());
+            for tag in new_tags {
+                if !list_item.has_tag(&tag) {
+                    list_item.tags.push(tag);
+                }
+            }
+        });
 
-        if self.display.len() > 0 {
-            self.selected_list_index = self.selected_list_index.clamp(0, self.display.len() - 1);
+        if self.apply_command(command) {
+            self.update_display(None);
+            self.save_list();
         }
-
-        self.update_display(None);
-        self.save_list();
     }
 
-    fn add_new_list_item(&mut self) {
-        let item = just_lists_core::list_item::ListItem::new("".to_string());
-        let mut current_item_path: Vec<String>;
+    /// Opens the selected item in `$VISUAL` or `$EDITOR`: just its text, or
+    /// with `outline` the item and everything below it, and applies the
+    /// changes once the editor exits.
+    fn edit_externally(&mut self, terminal: &mut DefaultTerminal, outline: bool) {
+        self.keys.clear();
 
-        if let Some(selected_list_entry) = self.get_current_display_item() {
-            if selected_list_entry.expanded {
-                self.handle_expand();
-            }
-        }
+        let Some(list_entry) = self.get_current_display_item() else {
+            return;
+        };
+        let item_id = list_entry.id_path.last().unwrap().clone();
 
-        if self.display.len() == 0 {
-            current_item_path = vec![item.id.clone()];
-            self.list.add_list_item(item.clone(), None);
-            self.display.insert(
-                0,
-                ListEntry {
-                    id_path: current_item_path.clone(),
-                    expanded: false,
-                },
-            )
+        let text = if outline {
+            write_outline(&self.list, &item_id)
         } else {
-            current_item_path = self
-                .display
-                .get(self.selected_list_index)
-                .unwrap()
-                .id_path
-                .clone();
+            self.list
+                .get_list_item(&item_id)
+                .map(|item| format!("{}\n", item.value))
+        };
+        let Some(text) = text else {
+            return;
+        };
 
-            let selected_item = current_item_path.remove(current_item_path.len() - 1);
-            current_item_path.push(item.id.clone());
+        // A new file with a random name, so nothing else can be put in its
+        // place. It is removed once dropped unless kept.
+        let file = tempfile::Builder::new()
+            .prefix(&format!("just-lists-{item_id}-"))
+            .suffix(".md")
+            .tempfile();
+        let mut file = match file {
+            std::result::Result::Ok(file) => file,
+            Err(error) => {
+                self.status_message = Some(format!("Unable to create a temporary file: {error}"));
+                return;
+            }
+        };
 
-            if current_item_path.len() == 1 {
-                let index_of_selection = match self.list.get_index_of_top_level_item(&selected_item)
-                {
-                    std::result::Result::Ok(index) => Some(index + 1),
-                    Err(_) => None,
-                };
+        let edited = match crate::terminal::edit_text(terminal, &mut file, &text) {
+            std::result::Result::Ok(edited) => edited,
+            Err(error) => {
+                self.status_message = Some(format!("Nothing was changed: {error}"));
+                return;
+            }
+        };
 
-                self.list.add_list_item(item, index_of_selection);
-            } else {
-                let parent_id = current_item_path[current_item_path.len() - 2].clone();
-                let index_of_child = match self.list.get_index_of_child(&selected_item, &parent_id)
-                {
-                    core::result::Result::Ok(index) => index,
-                    Err(_) => {
-                        return ();
-                    }
-                };
+        if edited == text {
+            return;
+        }
 
-                _ = self
-                    .list
-                    .add_child_list_item(item, &parent_id, Some(index_of_child + 1));
+        if !outline {
+            self.set_item_text(&item_id, edited.trim_end_matches(['\n', '\r']));
+            return;
+        }
+
+        // The file is kept when it can't be applied, so nothing typed is lost.
+        let keep = |error: &dyn std::fmt::Display| match file.keep() {
+            std::result::Result::Ok((_, path)) => {
+                format!("{error}. Your text is in {}", path.display())
             }
+            Err(keep_error) => format!("{error}. Your text could not be kept: {keep_error}"),
+        };
 
-            self.display.insert(
-                self.selected_list_index + 1,
-                ListEntry {
-                    id_path: current_item_path.clone(),
-                    expanded: false,
-                },
-            )
+        let parsed = parse_outline(&edited).and_then(|parsed| {
+            parsed.check(&self.list, &item_id)?;
+            std::result::Result::Ok(parsed)
+        });
+        let parsed = match parsed {
+            std::result::Result::Ok(parsed) => parsed,
+            Err(error) => {
+                self.status_message = Some(keep(&error));
+                return;
+            }
+        };
+
+        let result = self.history.execute(&mut self.list, |transaction| {
+            transaction.apply_outline(&item_id, &parsed)
+        });
+
+        if let Err(error) = result {
+            self.status_message = Some(keep(&error));
+            return;
         }
 
-        self.update_display(Some(current_item_path));
-        self.toggle_edit_mode();
+        self.update_display(None);
         self.save_list();
     }
 
-    fn insert_child_item(&mut self) {
-        if self.display.len() == 0 {
+    fn edit_notes(&mut self) {
+        let Some(list_entry) = self.get_current_display_item() else {
             return;
-        }
+        };
+        let Some(list_item) = self.list.get_list_item(list_entry.id_path.last().unwrap()) else {
+            return;
+        };
 
-        let item = just_lists_core::list_item::ListItem::new("".to_string());
+        self.editor = TextEditor::multiline(list_item.notes.as_deref().unwrap_or_default());
+        self.state = UIState::NotesView;
+    }
 
-        let parent_path = self
-            .display
-            .get(self.selected_list_index)
-            .unwrap()
-            .id_path
-            .clone();
+    fn save_notes(&mut self) {
+        self.state = UIState::ListView;
 
-        let mut child_path = parent_path.clone();
-        child_path.push(item.id.clone());
+        let Some(list_entry) = self.get_current_display_item() else {
+            return;
+        };
+        let item_id = list_entry.id_path.last().unwrap().clone();
 
-        _ = self
+        let text = self.editor.text().trim_end();
+        let notes = (!text.trim().is_empty()).then(|| text.to_string());
+
+        if self
             .list
-            .add_child_list_item(item, parent_path.last().unwrap(), None);
+            .get_list_item(&item_id)
+            .is_some_and(|item| item.notes == notes)
+        {
+            return;
+        }
 
-        let current_item = self.display.get_mut(self.selected_list_index).unwrap();
+        let command = Command::edit(&self.list, &item_id, |list_item| {
+            list_item.notes = notes;
+        });
 
-        if !current_item.expanded {
-            self.handle_expand();
+        if self.apply_command(command) {
+            self.update_display(None);
+            self.save_list();
+        }
+    }
+
+    /// The displayed sibling before or after the entry at `index`.
+    fn sibling_entry(&self, index: usize, forwards: bool) -> Option<&ListEntry> {
+        let depth = self.display.get(index)?.id_path.len();
+        // Entries are in tree order, so the closest entry that is not nested
+        // deeper is either a sibling or outside the parent.
+        let not_nested = |entry: &&ListEntry| entry.id_path.len() <= depth;
+
+        if forwards {
+            self.display[index + 1..].iter().find(not_nested)
         } else {
-            self.handle_expand();
-            self.handle_expand();
+            self.display[..index].iter().rev().find(not_nested)
         }
+        .filter(|entry| entry.id_path.len() == depth)
+    }
 
-        self.update_display(Some(child_path));
-        self.toggle_edit_mode();
+    /// Moving follows what is shown, which only matches the list while it is
+    /// shown in list order.
+    fn can_move_items(&mut self) -> bool {
+        if self.view.sort != SortMode::Manual {
+            self.status_message =
+                Some("Items can only be moved in list order (o to change)".to_string());
+            return false;
+        }
 
-        self.save_list();
+        !self.display.is_empty()
     }
 
-    fn toggle_item_completion(&mut self) {
-        if self.display.len() == 0 {
+    /// Swaps the selected item with the sibling shown above or below it.
+    fn move_selected_item(&mut self, forwards: bool) {
+        if !self.can_move_items() {
             return;
         }
 
-        let list_entry = self.display.get(self.selected_list_index).unwrap();
-        let list_item = self
-            .list
-            .get_mut_list_item(list_entry.id_path.last().unwrap())
-            .unwrap();
+        let Some(sibling) = self.sibling_entry(self.selected_list_index, forwards) else {
+            return;
+        };
 
-        match list_item.state {
-            State::Pending => {
-                list_item.state = State::Completed;
-            }
-            State::Completed => list_item.state = State::Blocked,
-            State::Blocked => list_item.state = State::Pending,
+        let id_path = self.display[self.selected_list_index].id_path.clone();
+        let parent_id = App::get_parent_from_path(&id_path).map(|p| p.to_string());
+        let std::result::Result::Ok(index) = self
+            .list
+            .get_index_in(sibling.id_path.last().unwrap(), parent_id.as_deref())
+        else {
+            return;
         };
 
-        self.save_list();
+        self.move_item(&id_path, parent_id.as_deref(), index, id_path.clone());
     }
 
-    fn toggle_edit_mode(&mut self) {
-        match self.state {
-            UIState::ListView => {
-                if self.display.len() == 0 {
-                    return;
-                }
-
-                let list_entry = self.display.get(self.selected_list_index).unwrap();
-                let list_item = self
-                    .list
-                    .get_list_item(list_entry.id_path.last().unwrap())
-                    .unwrap();
-                self.edit_text = list_item.value.clone();
-                self.cursor_index = self.edit_text.len() as i32;
-                self.state = UIState::EditView
-            }
-            UIState::EditView => self.state = UIState::ListView,
+    /// Makes the selected item the last child of the sibling shown above it.
+    fn indent_selected_item(&mut self) {
+        if !self.can_move_items() {
+            return;
         }
+
+        let Some(sibling_path) = self
+            .sibling_entry(self.selected_list_index, false)
+            .map(|sibling| sibling.id_path.clone())
+        else {
+            return;
+        };
+
+        let id_path = self.display[self.selected_list_index].id_path.clone();
+        let mut new_path = sibling_path.clone();
+        new_path.push(id_path.last().unwrap().clone());
+
+        self.expanded_items.insert(sibling_path.clone());
+        self.move_item(
+            &id_path,
+            sibling_path.last().map(|s| s.as_str()),
+            usize::MAX,
+            new_path,
+        );
     }
 
-    fn handle_backspace(&mut self) {
-        if self.edit_text.len() > 0 {
-            self.edit_text.remove((self.cursor_index - 1) as usize);
+    /// Moves the selected item out of its parent, placing it right after it.
+    fn outdent_selected_item(&mut self) {
+        if !self.can_move_items() {
+            return;
+        }
+
+        let id_path = self.display[self.selected_list_index].id_path.clone();
+        let parent_path = &id_path[..id_path.len() - 1];
+
+        if parent_path.is_empty() || self.display_parent_item.as_deref() == Some(parent_path) {
+            return;
         }
-        self.cursor_index = (self.cursor_index - 1).clamp(0, self.edit_text.len() as i32);
-    }
 
-    fn handle_cursor_left(&mut self) {
-        self.cursor_index = (self.cursor_index - 1).clamp(0, self.edit_text.len() as i32)
-    }
+        let grandparent_id = App::get_parent_from_path(parent_path);
+        let std::result::Result::Ok(parent_index) = self
+            .list
+            .get_index_in(parent_path.last().unwrap(), grandparent_id)
+        else {
+            return;
+        };
 
-    fn handle_cursor_right(&mut self) {
-        self.cursor_index = (self.cursor_index + 1).clamp(0, self.edit_text.len() as i32)
-    }
+        let mut new_path = parent_path[..parent_path.len() - 1].to_vec();
+        new_path.push(id_path.last().unwrap().clone());
 
-    fn handle_text_input(&mut self, c: char) {
-        self.edit_text.insert(self.cursor_index as usize, c);
-        self.cursor_index += 1;
+        self.move_item(&id_path, grandparent_id, parent_index + 1, new_path);
     }
 
-    fn save_edited_text(&mut self) {
-        let list_item = self
-            .list
-            .get_mut_list_item(
-                self.display
-                    .get(self.selected_list_index)
-                    .unwrap()
-                    .id_path
-                    .last()
-                    .unwrap(),
-            )
-            .unwrap();
-        list_item.value = self.edit_text.clone();
-        self.state = UIState::ListView;
+    fn move_item(
+        &mut self,
+        id_path: &[String],
+        to_parent_id: Option<&str>,
+        index: usize,
+        new_path: Vec<String>,
+    ) {
+        let command = Command::move_item(
+            &self.list,
+            id_path.last().unwrap(),
+            App::get_parent_from_path(id_path),
+            to_parent_id,
+            index,
+        );
+
+        if !self.apply_command(command) {
+            return;
+        }
+
+        // Keep whatever was expanded below the item expanded.
+        let moved_paths: Vec<Vec<String>> = self
+            .expanded_items
+            .iter()
+            .filter(|path| path.starts_with(id_path))
+            .cloned()
+            .collect();
+
+        for path in moved_paths {
+            self.expanded_items.remove(&path);
+            let mut moved_path = new_path.clone();
+            moved_path.extend_from_slice(&path[id_path.len()..]);
+            self.expanded_items.insert(moved_path);
+        }
 
+        self.update_display(Some(new_path));
         self.save_list();
     }
 
@@ -866,7 +2787,7 @@ impl App {
             }
         }
 
-        if new_path.len() > 0 {
+        if !new_path.is_empty() {
             self.display_parent_item = Some(new_path);
         } else {
             self.display_parent_item = None;
@@ -898,44 +2819,221 @@ impl App {
         }
     }
 
-    fn paste(&mut self) {
-        if let Some(clipboard) = &self.clipboard {
-            let current_selected_item = self.get_current_display_item().cloned();
-            if let Some(selected_item) = current_selected_item {
-                _ = self.list.add_existing_child_list_item(
-                    &clipboard.list_item_id,
-                    selected_item.id_path.last().unwrap(),
-                    None,
-                );
+    fn show_locations(&mut self) {
+        let Some(id_path) = self.get_current_display_item().map(|e| e.id_path.clone()) else {
+            return;
+        };
+
+        self.locations = self.list.get_locations(id_path.last().unwrap());
+        self.selected_location = self
+            .locations
+            .iter()
+            .position(|location| *location == id_path)
+            .unwrap_or(0);
+        self.state = UIState::LocationsView;
+    }
+
+    /// Replaces the selected occurrence of a shared item with an
+    /// independent copy.
+    fn unshare_selected_item(&mut self) {
+        let Some(id_path) = self.get_current_display_item().map(|e| e.id_path.clone()) else {
+            return;
+        };
+
+        let item_id = id_path.last().unwrap();
+        if self.list.reference_count(item_id) < 2 {
+            self.status_message = Some("This item only appears here".to_string());
+            return;
+        }
+
+        let parent_id = App::get_parent_from_path(&id_path);
+        let command = Command::unshare(&self.list, item_id, parent_id);
+
+        if self.apply_command(command) {
+            self.status_message = Some("The item here is now a copy of its own".to_string());
+            self.update_display(None);
+            self.save_list();
+        }
+    }
+
+    fn open_paste_menu(&mut self) {
+        if self.clipboard.is_none() {
+            self.status_message = Some("Copy (c) or cut (x) an item first".to_string());
+            return;
+        }
+
+        self.state = UIState::PasteView;
+    }
+
+    fn paste_from_menu(&mut self, key: char) {
+        let target = match key.to_ascii_lowercase() {
+            'c' => PasteTarget::Child,
+            'b' => PasteTarget::Before,
+            'a' => PasteTarget::After,
+            't' => PasteTarget::TopLevel,
+            _ => return,
+        };
+
+        self.state = UIState::ListView;
+        self.paste(target, key.is_ascii_uppercase());
+    }
+
+    /// Pastes the clipboard item at `target`, either as another reference to
+    /// the same item or, with `independent_copy`, as a copy of its subtree.
+    /// Cut items are always moved.
+    fn paste(&mut self, target: PasteTarget, independent_copy: bool) {
+        let Some(clipboard) = &self.clipboard else {
+            return;
+        };
+
+        let selected_path = self
+            .get_current_display_item()
+            .map(|list_entry| list_entry.id_path.clone());
+
+        let (parent_id, index) = match (target, &selected_path) {
+            (PasteTarget::TopLevel, _) => (None, None),
+            (_, None) => return,
+            (PasteTarget::Child, Some(path)) => (path.last().cloned(), None),
+            (PasteTarget::Before | PasteTarget::After, Some(path)) => {
+                let parent_id = App::get_parent_from_path(path);
+                let std::result::Result::Ok(selected_index) =
+                    self.list.get_index_in(path.last().unwrap(), parent_id)
+                else {
+                    return;
+                };
+                let offset = usize::from(target == PasteTarget::After);
+
+                (
+                    parent_id.map(|p| p.to_string()),
+                    Some(selected_index + offset),
+                )
             }
+        };
 
-            if let ClipboardAction::Cut(previous_parent_id) = &clipboard.action_type {
-                _ = self
-                    .list
-                    .remove_child_list_item(&clipboard.list_item_id, previous_parent_id.as_ref());
+        let item_id = &clipboard.list_item_id;
+
+        let command = match &clipboard.action_type {
+            ClipboardAction::Cut(previous_parent_id) => {
+                self.cut_paste_command(item_id, previous_parent_id.as_deref(), parent_id, index)
+            }
+            ClipboardAction::Copy if independent_copy => {
+                Command::add_copy(&self.list, item_id, parent_id.as_deref(), index)
+            }
+            ClipboardAction::Copy => {
+                Command::link(&self.list, item_id, parent_id.as_deref(), index)
             }
+        };
+
+        let result = command.and_then(|command| self.history.apply(&mut self.list, command));
+
+        if let Err(error) = result {
+            self.status_message = Some(format!("Unable to paste: {error}"));
+            return;
+        }
+
+        if let ClipboardAction::Cut(_) = clipboard.action_type {
+            // The item has moved, so the old parent is no longer valid to cut from.
+            self.clipboard = None;
         }
 
         self.update_display(None);
         self.save_list();
     }
 
-    fn update_display(&mut self, custom_selected_item: Option<Vec<String>>) {
-        let items_to_display: Vec<&just_lists_core::list_item::ListItem>;
+    /// Moves a cut item. `index` is where it goes among the new siblings as
+    /// they are now, which is one further than after the item is taken out
+    /// if it stays under the same parent and was in front.
+    fn cut_paste_command(
+        &self,
+        item_id: &str,
+        previous_parent_id: Option<&str>,
+        parent_id: Option<String>,
+        index: Option<usize>,
+    ) -> std::result::Result<Command, CommandError> {
+        let index = match (index, self.list.get_index_in(item_id, previous_parent_id)) {
+            (Some(index), std::result::Result::Ok(previous_index))
+                if previous_parent_id == parent_id.as_deref() && previous_index < index =>
+            {
+                index - 1
+            }
+            (index, _) => index.unwrap_or(usize::MAX),
+        };
 
-        let old_selected_entry = self.display.get(self.selected_list_index.clone()).cloned();
+        Command::move_item(
+            &self.list,
+            item_id,
+            previous_parent_id,
+            parent_id.as_deref(),
+            index,
+        )
+    }
 
-        match self.display_parent_item.clone() {
-            None => {
-                items_to_display = self.list.get_top_level_list_items();
-            }
-            Some(path) => {
-                items_to_display = self
-                    .list
-                    .get_children(self.list.get_list_item(path.last().unwrap()).unwrap());
-            }
+    fn is_valid_path(&self, path: &[String]) -> bool {
+        let Some(first) = path.first() else {
+            return false;
         };
 
+        if self.list.get_index_of_top_level_item(first).is_err() {
+            return false;
+        }
+
+        path.windows(2)
+            .all(|pair| self.list.get_index_of_child(&pair[1], &pair[0]).is_ok())
+    }
+
+    /// Forgets the focused item and clipboard when they refer to items that
+    /// no longer exist at that location.
+    fn drop_stale_references(&mut self) {
+        if let Some(path) = &self.display_parent_item
+            && !self.is_valid_path(path)
+        {
+            self.display_parent_item = None;
+        }
+
+        if let Some(clipboard) = &self.clipboard
+            && self.list.get_list_item(&clipboard.list_item_id).is_none()
+        {
+            self.clipboard = None;
+        }
+    }
+
+    /// Works out the progress of the items on display that it is not known
+    /// for yet.
+    fn update_progress(&mut self) {
+        for entry in &self.display {
+            let id = entry.id_path.last().unwrap();
+
+            if !self.progress.contains_key(id)
+                && let Some(progress) = self.list.progress(id, &self.config.workflow)
+            {
+                self.progress.insert(id.clone(), progress);
+            }
+        }
+    }
+
+    fn update_display(&mut self, custom_selected_item: Option<Vec<String>>) {
+        self.drop_stale_references();
+
+        let old_selected_index = self.selected_list_index;
+        let old_selected_entry = self.display.get(self.selected_list_index).cloned();
+
+        let today = App::today();
+        let items_to_display: Vec<String> = match self.display_parent_item.clone() {
+            None => self.view.visible(
+                &self.list,
+                self.list.get_top_level_list_items(),
+                today,
+                &self.config.workflow,
+            ),
+            Some(path) => self.visible_children(
+                self.list.get_list_item(path.last().unwrap()).unwrap(),
+                today,
+            ),
+        }
+        .into_iter()
+        .map(|item| item.id.clone())
+        .collect();
+
         self.display.clear();
 
         for c in items_to_display {
@@ -945,41 +3043,54 @@ impl App {
                 self.display_parent_item.clone().unwrap()
             };
 
-            id_path.push(c.id.clone());
+            id_path.push(c);
 
             self.display.push(ListEntry {
-                id_path: id_path,
+                id_path,
                 expanded: false,
             })
         }
 
-        if self.display.len() > 0 {
+        if !self.display.is_empty() {
             self.selected_list_index = self.selected_list_index.clamp(0, self.display.len() - 1);
         }
 
         let mut display_index = 0;
 
+        // While filtering, everything leading to a match is expanded so the
+        // matches can be seen without changing what is expanded otherwise.
+        let filtering = self.view.is_filtering();
+
         while let Some(display_item) = self.display.get(display_index) {
-            if self.expanded_items.contains(&display_item.id_path) {
-                self.selected_list_index = display_index;
-                self.handle_expand();
+            let (id, ancestor_ids) = display_item.id_path.split_last().unwrap();
+            let list_item = self.list.get_list_item(id).unwrap();
+
+            // An item inside itself would be expanded forever in a cyclic list.
+            let is_cyclic = ancestor_ids.contains(id);
+
+            if self.expanded_items.contains(&display_item.id_path)
+                || (filtering && !is_cyclic && !self.visible_children(list_item, today).is_empty())
+            {
+                self.expand_entry(display_index);
             }
 
             display_index += 1;
         }
 
+        self.progress.clear();
+        self.update_progress();
+
         match custom_selected_item {
             None => {
-                if self.display.len() > 0 {
-                    if let Some(old_selected_entry) = old_selected_entry {
-                        if let Some(index) = self
-                            .display
-                            .iter()
-                            .position(|e| e.id_path == old_selected_entry.id_path)
-                        {
-                            self.selected_list_index = index;
-                        }
-                    }
+                if !self.display.is_empty()
+                    && let Some(old_selected_entry) = old_selected_entry
+                {
+                    // Stay close to where the selection was if the item is gone.
+                    self.selected_list_index = self
+                        .display
+                        .iter()
+                        .position(|e| e.id_path == old_selected_entry.id_path)
+                        .unwrap_or(old_selected_index.min(self.display.len() - 1));
                 }
             }
             Some(select_id_path) => {
@@ -997,3 +3108,29 @@ impl App {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filtering_a_cyclic_list_stops_at_the_repeated_item() {
+        let mut app = App::new(None, false, Config::default());
+        app.list = List::try_from_str_unchecked(
+            r#"{
+                "list_item_store": {
+                    "a": { "id": "a", "value": "A", "children": ["b"], "state": "Pending" },
+                    "b": { "id": "b", "value": "B", "children": ["a"], "state": "Pending" }
+                },
+                "top_level_items": ["a"]
+            }"#,
+        )
+        .unwrap();
+        app.view.state_filter = Some(State::Pending);
+
+        app.update_display(None);
+
+        let paths: Vec<Vec<String>> = app.display.iter().map(|e| e.id_path.clone()).collect();
+        assert_eq!(paths, vec![vec!["a"], vec!["a", "b"], vec!["a", "b", "a"]]);
+    }
+}
@@ -0,0 +1,67 @@
+use color_eyre::Result;
+use color_eyre::eyre::WrapErr;
+use just_lists_core::list::List;
+use just_lists_core::storage::{Storage, StorageError};
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Runs `jl check`, printing every integrity issue found in `file` and
+/// repairing the file when `fix` is set. The damaged version is kept as a
+/// backup.
+///
+/// Fixing takes the same lock as the list view, so a list that is open
+/// elsewhere is left alone.
+pub fn run(file: &Path, fix: bool) -> Result<ExitCode> {
+    let mut storage = Storage::new(file);
+
+    let _lock = if fix {
+        match storage.lock() {
+            Ok(lock) => Some(lock),
+            Err(error @ StorageError::Locked { .. }) => {
+                eprintln!("{}: {}, close it before fixing", file.display(), error);
+                return Ok(ExitCode::FAILURE);
+            }
+            Err(error) => {
+                return Err(error).wrap_err_with(|| format!("Unable to lock {}", file.display()));
+            }
+        }
+    } else {
+        None
+    };
+
+    let content =
+        fs::read_to_string(file).wrap_err_with(|| format!("Unable to read {}", file.display()))?;
+
+    let mut list = match List::try_from_str_unchecked(&content) {
+        Ok(list) => list,
+        Err(error) => {
+            eprintln!("{}: {}", file.display(), error);
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+
+    let issues = if fix { list.repair() } else { list.validate() };
+
+    if issues.is_empty() {
+        println!("{}: no problems found", file.display());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    println!("{}: {} problem(s) found", file.display(), issues.len());
+    for issue in issues.iter() {
+        println!("  - {issue}");
+    }
+
+    if !fix {
+        println!("Run `jl check --fix {}` to repair them.", file.display());
+        return Ok(ExitCode::FAILURE);
+    }
+
+    storage
+        .save(&list)
+        .wrap_err_with(|| format!("Unable to save {}", file.display()))?;
+    println!("Repaired {} problem(s).", issues.len());
+
+    Ok(ExitCode::SUCCESS)
+}
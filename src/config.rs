@@ -0,0 +1,176 @@
+use crate::keymap::Keymap;
+use just_lists_core::list_item::State;
+use just_lists_core::workflow::{StateDefinition, Workflow, WorkflowError};
+use ratatui::style::Color;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Settings read from `config.toml`. Everything is optional; a missing file
+/// gives the defaults.
+#[derive(Default)]
+pub struct Config {
+    pub workflow: Workflow,
+    /// Complete a parent once all of its children are done. Only checked
+    /// when an item's state is changed, not when items are added, pasted or
+    /// moved under a parent.
+    pub auto_complete_parents: bool,
+    pub keymap: Keymap,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    states: Vec<StateConfig>,
+    #[serde(default)]
+    auto_complete_parents: bool,
+    #[serde(default)]
+    keys: KeysConfig,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct KeysConfig {
+    preset: Option<String>,
+    /// The keys of each action, replacing those of the preset.
+    #[serde(default)]
+    bindings: BTreeMap<String, KeyList>,
+}
+
+/// One key or several.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StateConfig {
+    name: String,
+    glyph: String,
+    color: String,
+    highlight: Option<String>,
+    #[serde(default)]
+    done: bool,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(PathBuf, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, error) => {
+                write!(f, "Unable to read {}: {}", path.display(), error)
+            }
+            ConfigError::Parse(path, error) => write!(f, "{}: {}", path.display(), error),
+            ConfigError::Invalid(path, message) => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Loads `path`, or `config.toml` in the user's config directory when no
+    /// path is given. Only an explicitly given file has to exist.
+    pub fn load(path: Option<&Path>) -> Result<Config, ConfigError> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match Config::default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound && !required => {
+                return Ok(Config::default());
+            }
+            Err(error) => return Err(ConfigError::Io(path, error)),
+        };
+
+        let file: ConfigFile =
+            toml::from_str(&content).map_err(|error| ConfigError::Parse(path.clone(), error))?;
+
+        Config::from_file(file).map_err(|message| ConfigError::Invalid(path, message))
+    }
+
+    /// `just-lists/config.toml` in `$XDG_CONFIG_HOME`, or in `~/.config` when
+    /// that is unset, on every platform. Only without a home directory does
+    /// it fall back to the platform's own config directory.
+    fn default_path() -> Option<PathBuf> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| dirs::home_dir().map(|home| home.join(".config")))
+            .or_else(dirs::config_dir)?;
+
+        Some(config_dir.join("just-lists").join("config.toml"))
+    }
+
+    fn from_file(file: ConfigFile) -> Result<Config, String> {
+        let workflow = if file.states.is_empty() {
+            Workflow::default()
+        } else {
+            let states = file
+                .states
+                .into_iter()
+                .map(StateConfig::into_definition)
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Workflow::new(states).map_err(|error: WorkflowError| error.to_string())?
+        };
+
+        let overrides = file
+            .keys
+            .bindings
+            .into_iter()
+            .map(|(action, keys)| match keys {
+                KeyList::One(key) => (action, vec![key]),
+                KeyList::Many(keys) => (action, keys),
+            })
+            .collect();
+
+        let keymap = Keymap::new(file.keys.preset.as_deref().unwrap_or("default"), &overrides)?;
+
+        Ok(Config {
+            workflow,
+            auto_complete_parents: file.auto_complete_parents,
+            keymap,
+        })
+    }
+}
+
+impl StateConfig {
+    fn into_definition(self) -> Result<StateDefinition, String> {
+        for color in std::iter::once(&self.color).chain(&self.highlight) {
+            if Color::from_str(color).is_err() {
+                return Err(format!(
+                    "`{}` of state `{}` is not a colour",
+                    color, self.name
+                ));
+            }
+        }
+
+        Ok(StateDefinition {
+            state: State::from(self.name),
+            glyph: self.glyph,
+            color: self.color,
+            highlight: self.highlight,
+            done: self.done,
+        })
+    }
+}
@@ -0,0 +1,475 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Text being typed into a popup, with a cursor and an optional selection.
+/// Positions are byte offsets that always fall between grapheme clusters, so
+/// accented letters, emoji and wide characters move and delete as one.
+pub struct TextEditor {
+    text: String,
+    cursor: usize,
+    /// Where the selection started; it runs from here to the cursor.
+    anchor: Option<usize>,
+    /// The column Up and Down try to keep, set by the first of them.
+    preferred_column: Option<usize>,
+    multiline: bool,
+}
+
+impl TextEditor {
+    /// A single line editor with the cursor at the end of `text`.
+    pub fn new(text: &str) -> TextEditor {
+        TextEditor {
+            text: text.to_string(),
+            cursor: text.len(),
+            anchor: None,
+            preferred_column: None,
+            multiline: false,
+        }
+    }
+
+    /// An editor where Shift-Enter or Alt-Enter starts a new line.
+    pub fn multiline(text: &str) -> TextEditor {
+        TextEditor {
+            multiline: true,
+            ..TextEditor::new(text)
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Applies an editing key, returning whether the text changed. Motions
+    /// that depend on wrapping use `width` columns per line.
+    pub fn handle_key(&mut self, key: KeyEvent, width: usize) -> bool {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let select = key.modifiers.contains(KeyModifiers::SHIFT);
+        let by_word = control || alt;
+
+        if !matches!(key.code, KeyCode::Up | KeyCode::Down) {
+            self.preferred_column = None;
+        }
+
+        match key.code {
+            KeyCode::Char('a') if control => self.move_to(self.line_start(width), select),
+            KeyCode::Char('e') if control => self.move_to(self.line_end(width), select),
+            KeyCode::Char('w') if control => return self.delete_before(self.word_start()),
+            // Both at once is how some terminals send AltGr characters.
+            KeyCode::Char(_) if control != alt => (),
+            KeyCode::Char(c) => {
+                self.insert(&c.to_string());
+                return true;
+            }
+            KeyCode::Enter if self.multiline => {
+                self.insert("\n");
+                return true;
+            }
+            KeyCode::Backspace if by_word => return self.delete_before(self.word_start()),
+            KeyCode::Backspace => return self.delete_before(self.previous_boundary(self.cursor)),
+            KeyCode::Delete if by_word => return self.delete_after(self.word_end()),
+            KeyCode::Delete => return self.delete_after(self.next_boundary(self.cursor)),
+            KeyCode::Left if by_word => self.move_to(self.word_start(), select),
+            KeyCode::Right if by_word => self.move_to(self.word_end(), select),
+            KeyCode::Left => match self.selection() {
+                Some(selection) if !select => self.move_to(selection.start, false),
+                _ => self.move_to(self.previous_boundary(self.cursor), select),
+            },
+            KeyCode::Right => match self.selection() {
+                Some(selection) if !select => self.move_to(selection.end, false),
+                _ => self.move_to(self.next_boundary(self.cursor), select),
+            },
+            KeyCode::Home if control => self.move_to(0, select),
+            KeyCode::End if control => self.move_to(self.text.len(), select),
+            KeyCode::Home => self.move_to(self.line_start(width), select),
+            KeyCode::End => self.move_to(self.line_end(width), select),
+            KeyCode::Up => self.move_vertically(-1, width, select),
+            KeyCode::Down => self.move_vertically(1, width, select),
+            _ => (),
+        }
+
+        false
+    }
+
+    /// Inserts text pasted into the terminal. Single line editors get its
+    /// lines joined with spaces.
+    pub fn paste(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+
+        if self.multiline {
+            self.insert(&text);
+        } else {
+            self.insert(&text.lines().collect::<Vec<_>>().join(" "));
+        }
+    }
+
+    /// The selected bytes, if anything is selected.
+    fn selection(&self) -> Option<Range<usize>> {
+        self.anchor
+            .filter(|anchor| *anchor != self.cursor)
+            .map(|anchor| anchor.min(self.cursor)..anchor.max(self.cursor))
+    }
+
+    /// Replaces the selection, or inserts at the cursor.
+    fn insert(&mut self, text: &str) {
+        let range = self.selection().unwrap_or(self.cursor..self.cursor);
+        self.text.replace_range(range.clone(), text);
+        self.cursor = range.start + text.len();
+        self.anchor = None;
+    }
+
+    /// Deletes the selection, or from `start` to the cursor.
+    fn delete_before(&mut self, start: usize) -> bool {
+        let range = self.selection().unwrap_or(start..self.cursor);
+        self.delete(range)
+    }
+
+    /// Deletes the selection, or from the cursor to `end`.
+    fn delete_after(&mut self, end: usize) -> bool {
+        let range = self.selection().unwrap_or(self.cursor..end);
+        self.delete(range)
+    }
+
+    fn delete(&mut self, range: Range<usize>) -> bool {
+        self.anchor = None;
+        self.cursor = range.start;
+
+        if range.is_empty() {
+            return false;
+        }
+
+        self.text.replace_range(range, "");
+        true
+    }
+
+    fn move_to(&mut self, position: usize, select: bool) {
+        if select {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+
+        self.cursor = position;
+    }
+
+    fn previous_boundary(&self, position: usize) -> usize {
+        self.text[..position]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(index, _)| index)
+    }
+
+    fn next_boundary(&self, position: usize) -> usize {
+        self.text[position..]
+            .graphemes(true)
+            .next()
+            .map_or(position, |grapheme| position + grapheme.len())
+    }
+
+    /// The start of the word before the cursor, skipping anything between.
+    fn word_start(&self) -> usize {
+        let mut graphemes = self.text[..self.cursor]
+            .grapheme_indices(true)
+            .rev()
+            .peekable();
+        let mut position = self.cursor;
+
+        while let Some((index, _)) = graphemes.next_if(|(_, g)| !is_word(g)) {
+            position = index;
+        }
+
+        while let Some((index, _)) = graphemes.next_if(|(_, g)| is_word(g)) {
+            position = index;
+        }
+
+        position
+    }
+
+    /// The end of the word after the cursor, skipping anything between.
+    fn word_end(&self) -> usize {
+        let mut graphemes = self.text[self.cursor..].graphemes(true).peekable();
+        let mut position = self.cursor;
+
+        while let Some(grapheme) = graphemes.next_if(|g| !is_word(g)) {
+            position += grapheme.len();
+        }
+
+        while let Some(grapheme) = graphemes.next_if(|g| is_word(g)) {
+            position += grapheme.len();
+        }
+
+        position
+    }
+
+    /// The byte ranges of the lines the text is shown as, wrapped at word
+    /// boundaries to fit `width` columns. Newlines are not included.
+    fn rows(&self, width: usize) -> Vec<Range<usize>> {
+        let width = width.max(1);
+        let mut rows = Vec::new();
+        let mut offset = 0;
+
+        for line in self.text.split('\n') {
+            let mut start = 0;
+            let mut row_width = 0;
+            // Where the row can be broken, after the last whitespace.
+            let mut break_at = None;
+
+            for (index, grapheme) in line.grapheme_indices(true) {
+                // Whitespace may run past the edge rather than start a row.
+                if grapheme.chars().all(char::is_whitespace) {
+                    row_width += grapheme.width();
+                    break_at = Some(index + grapheme.len());
+                    continue;
+                }
+
+                let grapheme_width = grapheme.width();
+
+                if row_width + grapheme_width > width && index > start {
+                    let end = break_at.filter(|&end| end > start).unwrap_or(index);
+                    rows.push(offset + start..offset + end);
+                    start = end;
+                    row_width = line[start..index].width();
+                    break_at = None;
+
+                    // A word too long for a row of its own is split.
+                    if row_width + grapheme_width > width && index > start {
+                        rows.push(offset + start..offset + index);
+                        start = index;
+                        row_width = 0;
+                    }
+                }
+
+                row_width += grapheme_width;
+            }
+
+            rows.push(offset + start..offset + line.len());
+            offset += line.len() + 1;
+        }
+
+        rows
+    }
+
+    /// The row the cursor is on. At a soft wrap it belongs to the next row.
+    fn cursor_row(&self, rows: &[Range<usize>]) -> usize {
+        rows.iter()
+            .rposition(|row| row.start <= self.cursor)
+            .unwrap_or(0)
+    }
+
+    /// The furthest the cursor can go on `row`. Before a soft wrap that is
+    /// the start of the last grapheme, so it stays on the row.
+    fn row_end(&self, rows: &[Range<usize>], row: usize) -> usize {
+        let range = &rows[row];
+
+        match rows.get(row + 1) {
+            Some(next) if next.start == range.end && range.end > range.start => {
+                self.previous_boundary(range.end)
+            }
+            _ => range.end,
+        }
+    }
+
+    fn line_start(&self, width: usize) -> usize {
+        let rows = self.rows(width);
+        rows[self.cursor_row(&rows)].start
+    }
+
+    fn line_end(&self, width: usize) -> usize {
+        let rows = self.rows(width);
+        self.row_end(&rows, self.cursor_row(&rows))
+    }
+
+    fn move_vertically(&mut self, direction: isize, width: usize, select: bool) {
+        let rows = self.rows(width);
+        let row = self.cursor_row(&rows);
+
+        let Some(target) = row
+            .checked_add_signed(direction)
+            .filter(|target| *target < rows.len())
+        else {
+            return;
+        };
+
+        let column = *self
+            .preferred_column
+            .get_or_insert_with(|| self.text[rows[row].start..self.cursor].width());
+
+        let end = self.row_end(&rows, target);
+        let mut position = rows[target].start;
+        let mut position_width = 0;
+
+        for (index, grapheme) in self.text[position..end].grapheme_indices(true) {
+            position_width += grapheme.width();
+
+            if position_width > column {
+                break;
+            }
+
+            position = rows[target].start + index + grapheme.len();
+        }
+
+        self.move_to(position, select);
+    }
+
+    /// The row and column of the cursor when wrapped to `width` columns.
+    pub fn cursor_position(&self, width: usize) -> (usize, usize) {
+        let rows = self.rows(width);
+        let row = self.cursor_row(&rows);
+        (row, self.text[rows[row].start..self.cursor].width())
+    }
+
+    /// The text wrapped to `width` columns, with the selection highlighted.
+    pub fn lines(&self, width: usize) -> Vec<Line<'static>> {
+        let selection = self.selection().unwrap_or_default();
+        let selected = Style::new().add_modifier(Modifier::REVERSED);
+
+        self.rows(width)
+            .into_iter()
+            .map(|row| {
+                let start = selection.start.clamp(row.start, row.end);
+                let end = selection.end.clamp(row.start, row.end);
+
+                Line::from(vec![
+                    Span::from(self.text[row.start..start].to_string()),
+                    Span::styled(self.text[start..end].to_string(), selected),
+                    Span::from(self.text[end..row.end].to_string()),
+                ])
+            })
+            .collect()
+    }
+}
+
+fn is_word(grapheme: &str) -> bool {
+    grapheme.chars().any(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A family emoji: four people joined by zero width joiners.
+    const FAMILY: &str = "👩\u{200d}👩\u{200d}👧\u{200d}👦";
+    /// An `é` written as an `e` and a combining accent.
+    const E_ACUTE: &str = "e\u{301}";
+
+    fn editor_at(text: &str, cursor: usize) -> TextEditor {
+        TextEditor {
+            cursor,
+            ..TextEditor::multiline(text)
+        }
+    }
+
+    fn row_texts(editor: &TextEditor, width: usize) -> Vec<&str> {
+        editor
+            .rows(width)
+            .into_iter()
+            .map(|row| &editor.text[row])
+            .collect()
+    }
+
+    #[test]
+    fn rows_wrap_wide_characters_by_columns() {
+        let editor = TextEditor::multiline("日本語のテキスト");
+
+        assert_eq!(row_texts(&editor, 6), vec!["日本語", "のテキ", "スト"]);
+        assert_eq!(row_texts(&editor, 5), vec!["日本", "語の", "テキ", "スト"]);
+
+        let editor = TextEditor::multiline("hello 世界 wide\n\n終わり");
+        assert_eq!(
+            row_texts(&editor, 8),
+            vec!["hello ", "世界 ", "wide", "", "終わり"]
+        );
+    }
+
+    #[test]
+    fn rows_never_split_grapheme_clusters() {
+        let editor = TextEditor::multiline(&FAMILY.repeat(3));
+        assert_eq!(row_texts(&editor, 1), vec![FAMILY; 3]);
+
+        let editor = TextEditor::multiline(&E_ACUTE.repeat(5));
+        assert_eq!(
+            row_texts(&editor, 2),
+            vec![E_ACUTE.repeat(2), E_ACUTE.repeat(2), E_ACUTE.to_string()]
+        );
+    }
+
+    #[test]
+    fn moving_up_and_down_keeps_the_column() {
+        // From column 3, Down lands after 日 as 本 would pass the column.
+        let mut editor = editor_at("abcdef\n日本語のテ", 3);
+        editor.move_vertically(1, 20, false);
+        assert_eq!(editor.cursor, "abcdef\n日".len());
+        assert_eq!(editor.cursor_position(20), (1, 2));
+
+        editor.move_vertically(-1, 20, false);
+        assert_eq!(editor.cursor, 3);
+
+        // Combining marks take no column of their own.
+        let text = format!("{}\nabcd", E_ACUTE.repeat(3));
+        let mut editor = editor_at(&text, E_ACUTE.len() * 3);
+        editor.move_vertically(1, 20, false);
+        assert_eq!(&text[editor.cursor..], "d");
+
+        editor.move_vertically(-1, 20, false);
+        assert_eq!(editor.cursor, E_ACUTE.len() * 3);
+
+        // Emoji are stepped over whole.
+        let text = format!("{FAMILY}{FAMILY}\nabcdefgh");
+        let mut editor = editor_at(&text, text.len());
+        editor.move_vertically(-1, 20, false);
+        assert!(text.is_char_boundary(editor.cursor));
+        assert!(
+            [0, FAMILY.len(), FAMILY.len() * 2].contains(&editor.cursor),
+            "{} is inside an emoji",
+            editor.cursor
+        );
+
+        // Up from the first row and Down from the last stay put.
+        let mut editor = editor_at("日本", 3);
+        editor.move_vertically(-1, 20, false);
+        editor.move_vertically(1, 20, false);
+        assert_eq!(editor.cursor, 3);
+    }
+
+    #[test]
+    fn words_span_letters_of_any_script() {
+        let text = format!("日本語 caf{E_ACUTE}! {FAMILY} x");
+        let cafe = "日本語 ".len();
+        let after_cafe = cafe + "caf".len() + E_ACUTE.len();
+        let x = text.len() - 1;
+
+        assert_eq!(editor_at(&text, text.len()).word_start(), x);
+        // Punctuation and emoji between words are skipped.
+        assert_eq!(editor_at(&text, x).word_start(), cafe);
+        assert_eq!(editor_at(&text, cafe).word_start(), 0);
+
+        assert_eq!(editor_at(&text, 0).word_end(), "日本語".len());
+        assert_eq!(editor_at(&text, "日本語".len()).word_end(), after_cafe);
+        assert_eq!(editor_at(&text, after_cafe).word_end(), x + 1);
+        // The accent belongs to its letter.
+        assert_eq!(editor_at(&text, cafe + "caf".len()).word_end(), after_cafe);
+    }
+
+    #[test]
+    fn cursor_position_counts_columns() {
+        let text = "日本語のテキスト";
+
+        assert_eq!(editor_at(text, text.len()).cursor_position(6), (2, 4));
+        // At a soft wrap the cursor is shown at the start of the next row.
+        assert_eq!(editor_at(text, "日本語".len()).cursor_position(6), (1, 0));
+        assert_eq!(editor_at(text, "日".len()).cursor_position(6), (0, 2));
+
+        let text = format!("{E_ACUTE}{E_ACUTE}\n{FAMILY}a");
+        assert_eq!(
+            editor_at(&text, E_ACUTE.len() * 2).cursor_position(20),
+            (0, 2)
+        );
+        assert_eq!(
+            editor_at(&text, text.len()).cursor_position(20),
+            (1, FAMILY.width() + 1)
+        );
+    }
+}
@@ -0,0 +1,718 @@
+use crate::app::Message;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// A key together with the modifiers held down, like `ctrl-s` or `shift-up`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// Terminals report the same key in several ways, so bindings are
+    /// compared in one form: a character carries its own case rather than
+    /// Shift, and Shift-Tab is always `BackTab`.
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> KeyBinding {
+        let modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+
+        match code {
+            KeyCode::Char(c) => {
+                let c = if modifiers.contains(KeyModifiers::SHIFT) {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                };
+
+                // Terminals send Ctrl-/ and Ctrl-_ as Ctrl-7.
+                let c = match c {
+                    '/' | '_' if modifiers.contains(KeyModifiers::CONTROL) => '7',
+                    c => c,
+                };
+
+                KeyBinding {
+                    code: KeyCode::Char(c),
+                    modifiers: modifiers - KeyModifiers::SHIFT,
+                }
+            }
+            KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => KeyBinding {
+                code: KeyCode::BackTab,
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            KeyCode::BackTab => KeyBinding {
+                code,
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            code => KeyBinding { code, modifiers },
+        }
+    }
+
+    pub fn from_event(event: &KeyEvent) -> KeyBinding {
+        KeyBinding::new(event.code, event.modifiers)
+    }
+}
+
+const KEY_NAMES: [(&str, KeyCode); 16] = [
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("space", KeyCode::Char(' ')),
+    ("tab", KeyCode::Tab),
+    ("backtab", KeyCode::BackTab),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("insert", KeyCode::Insert),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+];
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    /// Parses keys written as modifiers and a key joined by `-`, like `j`,
+    /// `J`, `ctrl-s`, `alt-up`, `space` or `f5`.
+    fn from_str(text: &str) -> Result<KeyBinding, String> {
+        let invalid = || format!("`{text}` is not a key");
+
+        // The last part is the key itself, which may be `-`.
+        let (modifier_names, key) = match text.strip_suffix("--") {
+            Some(rest) => (rest, "-"),
+            None if text == "-" => ("", "-"),
+            None => match text.rsplit_once('-') {
+                Some((rest, key)) => (rest, key),
+                None => ("", text),
+            },
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+
+        for name in modifier_names.split('-').filter(|name| !name.is_empty()) {
+            modifiers |= match name.to_lowercase().as_str() {
+                "ctrl" | "c" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => return Err(invalid()),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => {
+                let lowercase = key.to_lowercase();
+                let name = match lowercase.as_str() {
+                    "return" => "enter",
+                    "escape" => "esc",
+                    "del" => "delete",
+                    name => name,
+                };
+
+                match KEY_NAMES.iter().find(|(key_name, _)| *key_name == name) {
+                    Some((_, code)) => *code,
+                    None => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                        Some(number @ 1..=12) => KeyCode::F(number),
+                        _ => return Err(invalid()),
+                    },
+                }
+            }
+        };
+
+        Ok(KeyBinding::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl-")?;
+        }
+
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt-")?;
+        }
+
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift-")?;
+        }
+
+        match self.code {
+            KeyCode::F(number) => write!(f, "f{number}"),
+            KeyCode::Char(c) if c != ' ' => write!(f, "{c}"),
+            code => match KEY_NAMES.iter().find(|(_, key_code)| *key_code == code) {
+                Some((name, _)) => write!(f, "{name}"),
+                None => write!(f, "{code:?}"),
+            },
+        }
+    }
+}
+
+/// Everything that can be bound in the list view, by the name used in
+/// `config.toml`.
+const ACTIONS: [(&str, Message); 47] = [
+    ("up", Message::Up),
+    ("down", Message::Down),
+    ("first", Message::First),
+    ("last", Message::Last),
+    ("previous_sibling", Message::PreviousSibling),
+    ("next_sibling", Message::NextSibling),
+    ("repeat", Message::Repeat),
+    ("expand", Message::Enter),
+    ("back", Message::FocusOnParentItem),
+    // Leaves a focused item first, like `back`, and quits at the top level.
+    ("quit", Message::Esc),
+    ("toggle_state", Message::Space),
+    ("edit", Message::Edit),
+    ("edit_in_editor", Message::EditInEditor),
+    ("outline_in_editor", Message::OutlineInEditor),
+    ("notes", Message::EditNotes),
+    ("notes_pane", Message::ToggleNotes),
+    ("new", Message::New),
+    ("insert_child", Message::InsertChild),
+    ("delete", Message::Delete),
+    ("delete_everywhere", Message::DeleteEverywhere),
+    ("focus", Message::FocusOnCurrentItem),
+    ("copy", Message::Copy),
+    ("cut", Message::Cut),
+    ("paste", Message::Paste),
+    ("paste_menu", Message::PasteMenu),
+    ("locations", Message::ShowLocations),
+    ("unshare", Message::Unshare),
+    ("move_up", Message::MoveUp),
+    ("move_down", Message::MoveDown),
+    ("indent", Message::Indent),
+    ("outdent", Message::Outdent),
+    ("undo", Message::Undo),
+    ("redo", Message::Redo),
+    ("save", Message::Save),
+    ("due_date", Message::SetDueDate),
+    ("scheduled_date", Message::SetScheduledDate),
+    ("sort", Message::CycleSort),
+    ("due_filter", Message::CycleDueFilter),
+    ("tags", Message::EditTags),
+    ("tag_filter", Message::FilterByTag),
+    ("raise_priority", Message::RaisePriority),
+    ("lower_priority", Message::LowerPriority),
+    ("search", Message::Search),
+    ("hide_completed", Message::ToggleHideCompleted),
+    ("blocked_only", Message::ToggleBlockedOnly),
+    ("archive", Message::Archive),
+    ("open_archive", Message::OpenArchive),
+];
+
+type Bindings = &'static [(&'static str, &'static [&'static str])];
+
+const DEFAULT_BINDINGS: Bindings = &[
+    ("up", &["up"]),
+    ("down", &["down"]),
+    ("first", &["home"]),
+    ("last", &["end"]),
+    ("repeat", &["."]),
+    ("expand", &["enter"]),
+    ("quit", &["esc", "q"]),
+    ("toggle_state", &["space"]),
+    ("edit", &["e"]),
+    ("edit_in_editor", &["E"]),
+    ("outline_in_editor", &["ctrl-e"]),
+    ("notes", &["N"]),
+    ("notes_pane", &["m"]),
+    ("new", &["n"]),
+    ("insert_child", &["i"]),
+    ("delete", &["d"]),
+    ("delete_everywhere", &["D"]),
+    ("focus", &["j"]),
+    ("copy", &["c"]),
+    ("cut", &["x"]),
+    ("paste", &["v"]),
+    ("paste_menu", &["P"]),
+    ("locations", &["w"]),
+    ("unshare", &["U"]),
+    ("move_up", &["shift-up"]),
+    ("move_down", &["shift-down"]),
+    ("indent", &["tab"]),
+    ("outdent", &["backtab"]),
+    ("undo", &["u"]),
+    ("redo", &["ctrl-r"]),
+    ("save", &["ctrl-s"]),
+    ("due_date", &["t"]),
+    ("scheduled_date", &["T"]),
+    ("sort", &["o"]),
+    ("due_filter", &["f"]),
+    ("tags", &["g"]),
+    ("tag_filter", &["#"]),
+    ("raise_priority", &["+", "="]),
+    ("lower_priority", &["-"]),
+    ("search", &["/"]),
+    ("hide_completed", &["h"]),
+    ("blocked_only", &["b"]),
+    ("archive", &["a"]),
+    ("open_archive", &["A"]),
+];
+
+/// Changes from the default bindings. `hjkl` move around the tree.
+const VIM_BINDINGS: Bindings = &[
+    ("up", &["k", "up"]),
+    ("down", &["j", "down"]),
+    ("first", &["g g", "home"]),
+    ("last", &["G", "end"]),
+    ("previous_sibling", &["{"]),
+    ("next_sibling", &["}"]),
+    ("delete", &["d d"]),
+    ("tags", &["g t"]),
+    ("focus", &["l"]),
+    ("back", &["h"]),
+    ("move_up", &["K", "shift-up"]),
+    ("move_down", &["J", "shift-down"]),
+    ("hide_completed", &["H"]),
+    ("new", &["o"]),
+    ("sort", &["s"]),
+    ("copy", &["y y"]),
+    ("paste", &["p"]),
+];
+
+/// Changes from the default bindings.
+const EMACS_BINDINGS: Bindings = &[
+    ("up", &["ctrl-p", "up"]),
+    ("down", &["ctrl-n", "down"]),
+    ("first", &["alt-<", "home"]),
+    ("last", &["alt->", "end"]),
+    ("focus", &["ctrl-f"]),
+    ("back", &["ctrl-b", "ctrl-g"]),
+    ("move_up", &["alt-p", "shift-up"]),
+    ("move_down", &["alt-n", "shift-down"]),
+    ("search", &["ctrl-s", "/"]),
+    ("save", &["alt-s"]),
+    ("copy", &["alt-w"]),
+    ("cut", &["ctrl-w"]),
+    ("paste", &["ctrl-y"]),
+    ("undo", &["ctrl-/", "u"]),
+    ("outline_in_editor", &["ctrl-x ctrl-e", "ctrl-e"]),
+];
+
+const PRESETS: [(&str, Bindings); 3] = [
+    ("default", &[]),
+    ("vim", VIM_BINDINGS),
+    ("emacs", EMACS_BINDINGS),
+];
+
+/// Parses keys pressed one after the other, separated by spaces, like
+/// `g g` or `ctrl-x ctrl-s`.
+fn parse_sequence(text: &str) -> Result<Vec<KeyBinding>, String> {
+    let keys = text
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<Vec<KeyBinding>, String>>()?;
+
+    if keys.is_empty() {
+        return Err(format!("`{text}` is not a key"));
+    }
+
+    Ok(keys)
+}
+
+fn describe_sequence(keys: &[KeyBinding]) -> String {
+    keys.iter()
+        .map(KeyBinding::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// What the keys typed so far add up to.
+pub enum Lookup {
+    Message(Message),
+    /// The start of a longer key sequence.
+    Prefix,
+    Unbound,
+}
+
+/// Which keys trigger which action in the list view. Text entry and the
+/// popups keep their fixed keys.
+pub struct Keymap {
+    bindings: HashMap<Vec<KeyBinding>, Message>,
+    /// Every proper start of a bound key sequence.
+    prefixes: HashSet<Vec<KeyBinding>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap::new("default", &BTreeMap::new()).expect("the default keymap is valid")
+    }
+}
+
+impl Keymap {
+    /// The keys of `preset`, with the keys of the actions in `overrides`
+    /// replaced. Fails on unknown names and on keys bound to more than one
+    /// action, or that also start a longer sequence, listing every conflict.
+    pub fn new(preset: &str, overrides: &BTreeMap<String, Vec<String>>) -> Result<Keymap, String> {
+        let Some((_, preset_bindings)) = PRESETS.iter().find(|(name, _)| *name == preset) else {
+            return Err(format!(
+                "`{preset}` is not a keymap preset, use one of {}",
+                PRESETS.map(|(name, _)| name).join(", ")
+            ));
+        };
+
+        let mut keys: Vec<Vec<String>> = ACTIONS.iter().map(|_| Vec::new()).collect();
+        let action_index = |action: &str| ACTIONS.iter().position(|(name, _)| *name == action);
+
+        for (action, action_keys) in DEFAULT_BINDINGS.iter().chain(preset_bindings.iter()) {
+            let index = action_index(action).expect("presets only bind known actions");
+            keys[index] = action_keys.iter().map(|key| key.to_string()).collect();
+        }
+
+        for (action, action_keys) in overrides {
+            let Some(index) = action_index(action) else {
+                return Err(format!("`{action}` is not an action that can be bound"));
+            };
+
+            keys[index] = action_keys.clone();
+        }
+
+        let mut bound_to: HashMap<Vec<KeyBinding>, usize> = HashMap::new();
+        let mut conflicts = Vec::new();
+
+        for (index, action_keys) in keys.iter().enumerate() {
+            for key in action_keys {
+                let sequence = parse_sequence(key)?;
+
+                match bound_to.get(&sequence) {
+                    Some(&other) if other != index => conflicts.push(format!(
+                        "`{}` is bound to both `{}` and `{}`",
+                        describe_sequence(&sequence),
+                        ACTIONS[other].0,
+                        ACTIONS[index].0
+                    )),
+                    Some(_) => (),
+                    None => {
+                        bound_to.insert(sequence, index);
+                    }
+                }
+            }
+        }
+
+        let mut prefixes = HashSet::new();
+
+        for (sequence, &index) in &bound_to {
+            for length in 1..sequence.len() {
+                let prefix = &sequence[..length];
+
+                if let Some(&other) = bound_to.get(prefix) {
+                    conflicts.push(format!(
+                        "`{}` is bound to `{}` but also starts `{}` of `{}`",
+                        describe_sequence(prefix),
+                        ACTIONS[other].0,
+                        describe_sequence(sequence),
+                        ACTIONS[index].0
+                    ));
+                }
+
+                prefixes.insert(prefix.to_vec());
+            }
+        }
+
+        if !conflicts.is_empty() {
+            conflicts.sort();
+            return Err(conflicts.join("; "));
+        }
+
+        Ok(Keymap {
+            bindings: bound_to
+                .into_iter()
+                .map(|(sequence, index)| (sequence, ACTIONS[index].1.clone()))
+                .collect(),
+            prefixes,
+        })
+    }
+
+    pub fn lookup(&self, keys: &[KeyBinding]) -> Lookup {
+        if let Some(message) = self.bindings.get(keys) {
+            Lookup::Message(message.clone())
+        } else if self.prefixes.contains(keys) {
+            Lookup::Prefix
+        } else {
+            Lookup::Unbound
+        }
+    }
+
+    /// Whether `key` can start a key sequence. Digits that can't are read as
+    /// a count.
+    pub fn starts_sequence(&self, key: KeyBinding) -> bool {
+        let keys = [key];
+        self.bindings.contains_key(&keys[..]) || self.prefixes.contains(&keys[..])
+    }
+}
+
+/// Counts and key sequences typed in the list view so far.
+#[derive(Default)]
+pub struct KeyReader {
+    /// Keys typed so far towards a key sequence.
+    pending: Vec<KeyBinding>,
+    /// A count typed in front of a key, like the 5 of `5j`.
+    count: Option<usize>,
+}
+
+impl KeyReader {
+    /// Counts are capped so that a mistyped one can't hang the app.
+    pub const MAX_COUNT: usize = 999;
+
+    /// Reads a key, returning a message once the keys typed add up to one.
+    /// The count typed before it is then left for [`KeyReader::take_count`].
+    /// Esc cancels whatever was typed so far.
+    pub fn read(&mut self, keymap: &Keymap, key: &KeyEvent) -> Option<Message> {
+        let binding = KeyBinding::from_event(key);
+
+        if key.code == KeyCode::Esc && self.typed().is_some() {
+            self.clear();
+            return None;
+        }
+
+        if let KeyCode::Char(c) = key.code
+            && let Some(digit) = c.to_digit(10)
+            && self.pending.is_empty()
+            && (digit != 0 || self.count.is_some())
+            && !keymap.starts_sequence(binding)
+        {
+            let count = self.count.unwrap_or(0) * 10 + digit as usize;
+            self.count = Some(count.min(Self::MAX_COUNT));
+            return None;
+        }
+
+        self.pending.push(binding);
+
+        match keymap.lookup(&self.pending) {
+            Lookup::Message(message) => {
+                self.pending.clear();
+                Some(message)
+            }
+            Lookup::Prefix => None,
+            Lookup::Unbound => {
+                self.clear();
+                None
+            }
+        }
+    }
+
+    pub fn take_count(&mut self) -> Option<usize> {
+        self.count.take()
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.count = None;
+    }
+
+    /// The count and keys typed so far, like `3g`, if there are any.
+    pub fn typed(&self) -> Option<String> {
+        if self.count.is_none() && self.pending.is_empty() {
+            return None;
+        }
+
+        let count = self
+            .count
+            .map(|count| count.to_string())
+            .unwrap_or_default();
+        Some(format!("{count}{}", describe_sequence(&self.pending)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(text: &str) -> KeyBinding {
+        text.parse().unwrap()
+    }
+
+    fn event(code: KeyCode, modifiers: KeyModifiers) -> KeyBinding {
+        KeyBinding::from_event(&KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn every_preset_builds_without_conflicts() {
+        for (preset, _) in PRESETS {
+            if let Err(error) = Keymap::new(preset, &BTreeMap::new()) {
+                panic!("The {preset} preset has conflicts: {error}");
+            }
+        }
+    }
+
+    #[test]
+    fn keys_match_the_events_terminals_send() {
+        assert_eq!(
+            key("ctrl--"),
+            event(KeyCode::Char('-'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(key("-"), event(KeyCode::Char('-'), KeyModifiers::NONE));
+
+        assert_eq!(key("shift-tab"), key("backtab"));
+        assert_eq!(key("shift-tab"), event(KeyCode::Tab, KeyModifiers::SHIFT));
+        assert_eq!(
+            key("shift-tab"),
+            event(KeyCode::BackTab, KeyModifiers::SHIFT)
+        );
+
+        assert_eq!(key("J"), event(KeyCode::Char('J'), KeyModifiers::SHIFT));
+        assert_eq!(key("J"), event(KeyCode::Char('j'), KeyModifiers::SHIFT));
+        assert_eq!(key("J"), key("shift-j"));
+        assert_ne!(key("J"), key("j"));
+
+        assert_eq!(
+            key("ctrl-/"),
+            event(KeyCode::Char('7'), KeyModifiers::CONTROL)
+        );
+        assert!("ctrl-nope".parse::<KeyBinding>().is_err());
+    }
+
+    #[test]
+    fn back_leaves_the_focused_item_and_quit_exits() {
+        let keymap = Keymap::new("vim", &BTreeMap::new()).unwrap();
+
+        assert!(matches!(
+            keymap.lookup(&[key("h")]),
+            Lookup::Message(Message::FocusOnParentItem)
+        ));
+        assert!(matches!(
+            keymap.lookup(&[key("q")]),
+            Lookup::Message(Message::Esc)
+        ));
+        assert!(matches!(
+            keymap.lookup(&[key("esc")]),
+            Lookup::Message(Message::Esc)
+        ));
+    }
+
+    fn keymap(preset: &str, overrides: &[(&str, &[&str])]) -> Result<Keymap, String> {
+        let overrides = overrides
+            .iter()
+            .map(|(action, keys)| {
+                let keys = keys.iter().map(|key| key.to_string()).collect();
+                (action.to_string(), keys)
+            })
+            .collect();
+
+        Keymap::new(preset, &overrides)
+    }
+
+    /// Types `keys`, separated by spaces, returning the messages they add up
+    /// to.
+    fn type_keys(reader: &mut KeyReader, keymap: &Keymap, keys: &str) -> Vec<Message> {
+        parse_sequence(keys)
+            .unwrap()
+            .into_iter()
+            .filter_map(|key| reader.read(keymap, &KeyEvent::new(key.code, key.modifiers)))
+            .collect()
+    }
+
+    #[test]
+    fn conflicting_sequences_and_prefixes_are_reported() {
+        let error = keymap("vim", &[("undo", &["d d"])]).err().unwrap();
+        assert!(
+            error.contains("`d d` is bound to both `delete` and `undo`"),
+            "{error}"
+        );
+
+        let error = keymap("vim", &[("copy", &["g"])]).err().unwrap();
+        assert!(
+            error.contains("`g` is bound to `copy` but also starts `g g` of `first`"),
+            "{error}"
+        );
+        assert!(
+            error.contains("`g` is bound to `copy` but also starts `g t` of `tags`"),
+            "{error}"
+        );
+
+        assert!(keymap("default", &[("copy", &["z z"])]).is_ok());
+        assert!(keymap("default", &[("nothing", &["z"])]).is_err());
+        assert!(keymap("qwerty", &[]).is_err());
+    }
+
+    #[test]
+    fn lookup_tells_sequences_from_their_prefixes() {
+        let keymap = keymap("emacs", &[]).unwrap();
+
+        assert!(matches!(keymap.lookup(&[key("ctrl-x")]), Lookup::Prefix));
+        assert!(matches!(
+            keymap.lookup(&[key("ctrl-x"), key("ctrl-e")]),
+            Lookup::Message(Message::OutlineInEditor)
+        ));
+        assert!(matches!(
+            keymap.lookup(&[key("ctrl-x"), key("ctrl-x")]),
+            Lookup::Unbound
+        ));
+        assert!(matches!(
+            keymap.lookup(&[key("ctrl-p")]),
+            Lookup::Message(Message::Up)
+        ));
+        assert!(matches!(keymap.lookup(&[key("ctrl-z")]), Lookup::Unbound));
+        assert!(matches!(keymap.lookup(&[]), Lookup::Unbound));
+    }
+
+    #[test]
+    fn counts_are_read_before_keys() {
+        let keymap = keymap("vim", &[]).unwrap();
+        let mut reader = KeyReader::default();
+
+        let messages = type_keys(&mut reader, &keymap, "1 0 j");
+        assert!(matches!(messages[..], [Message::Down]));
+        assert_eq!(reader.take_count(), Some(10));
+        assert_eq!(reader.take_count(), None);
+
+        // A count is read across a key sequence.
+        let messages = type_keys(&mut reader, &keymap, "3 d");
+        assert!(messages.is_empty());
+        assert_eq!(reader.typed().as_deref(), Some("3d"));
+        let messages = type_keys(&mut reader, &keymap, "d");
+        assert!(matches!(messages[..], [Message::Delete]));
+        assert_eq!(reader.take_count(), Some(3));
+
+        type_keys(&mut reader, &keymap, "9 9 9 9 9 j");
+        assert_eq!(reader.take_count(), Some(KeyReader::MAX_COUNT));
+    }
+
+    #[test]
+    fn zero_starts_no_count_unless_bound() {
+        let vim = keymap("vim", &[]).unwrap();
+        let mut reader = KeyReader::default();
+
+        let messages = type_keys(&mut reader, &vim, "0 j");
+        assert!(matches!(messages[..], [Message::Down]));
+        assert_eq!(reader.take_count(), None);
+
+        let messages = type_keys(&mut reader, &vim, "2 0 j");
+        assert!(matches!(messages[..], [Message::Down]));
+        assert_eq!(reader.take_count(), Some(20));
+
+        // Digits bound to an action are keys rather than counts.
+        let sort_on_zero = keymap("vim", &[("sort", &["0"])]).unwrap();
+
+        let messages = type_keys(&mut reader, &sort_on_zero, "0");
+        assert!(matches!(messages[..], [Message::CycleSort]));
+        assert_eq!(reader.take_count(), None);
+
+        let messages = type_keys(&mut reader, &sort_on_zero, "2 0");
+        assert!(matches!(messages[..], [Message::CycleSort]));
+        assert_eq!(reader.take_count(), Some(2));
+    }
+
+    #[test]
+    fn esc_cancels_what_was_typed() {
+        let keymap = keymap("vim", &[]).unwrap();
+        let mut reader = KeyReader::default();
+
+        assert!(type_keys(&mut reader, &keymap, "5 g esc").is_empty());
+        assert_eq!(reader.typed(), None);
+
+        let messages = type_keys(&mut reader, &keymap, "g j");
+        assert!(messages.is_empty(), "`g j` is not bound");
+        assert_eq!(reader.typed(), None);
+
+        let messages = type_keys(&mut reader, &keymap, "esc");
+        assert!(matches!(messages[..], [Message::Esc]));
+    }
+}
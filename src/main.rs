@@ -1,20 +1,42 @@
 #![doc = include_str!("../README.md")]
 
 mod app;
+mod check;
+mod config;
+mod editor;
+mod keymap;
+mod markdown;
+mod terminal;
+mod view;
+mod watch;
 
 use app::App;
 use clap::Parser;
 use color_eyre::Result;
-use ratatui;
+use config::Config;
+use std::process::ExitCode;
 
-use crate::app::Inputs;
+use crate::app::{Commands, Inputs};
 
-fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
     let inputs = Inputs::parse();
     color_eyre::install()?;
-    let terminal = ratatui::init();
-    let mut app = App::new(inputs.file);
+
+    if let Some(Commands::Check { file, fix }) = inputs.command {
+        return check::run(&file, fix);
+    }
+
+    let config = match Config::load(inputs.config.as_deref()) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{error}");
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+
+    let terminal = terminal::init();
+    let mut app = App::new(inputs.file, inputs.keep_history, config);
     let result = app.run(terminal);
-    ratatui::restore();
-    result
+    terminal::restore();
+    result.map(|_| ExitCode::SUCCESS)
 }
@@ -0,0 +1,300 @@
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+
+const CODE_STYLE: Style = Style::new().fg(Color::Yellow);
+const LINK_STYLE: Style = Style::new()
+    .fg(Color::LightBlue)
+    .add_modifier(Modifier::UNDERLINED);
+const MUTED_STYLE: Style = Style::new().fg(Color::DarkGray);
+
+/// Renders an item's notes for the terminal. Styles stand in for headings,
+/// emphasis, code and links, while lists, quotes and code blocks keep their
+/// layout. Wrapping is left to the widget showing the text.
+pub fn render(markdown: &str) -> Text<'static> {
+    let mut renderer = Renderer::default();
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+
+    for event in Parser::new_ext(markdown, options) {
+        renderer.event(event);
+    }
+
+    renderer.end_line();
+    Text::from(renderer.lines)
+}
+
+#[derive(Default)]
+struct Renderer {
+    lines: Vec<Line<'static>>,
+    /// The line being put together.
+    spans: Vec<Span<'static>>,
+    /// Inline styles in effect, innermost last.
+    styles: Vec<Style>,
+    /// The next number of every list the text is in, `None` for bullets.
+    lists: Vec<Option<u64>>,
+    /// The bullet or number starting the next line of a list item.
+    marker: Option<String>,
+    quote_depth: usize,
+    in_code_block: bool,
+    /// Whether a blank line separates the next block from the last one.
+    gap: bool,
+    /// The targets of the links the text is in, with their text so far.
+    links: Vec<(String, String)>,
+}
+
+impl Renderer {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) if self.in_code_block => self.push_lines(&text, CODE_STYLE),
+            Event::Text(text) => self.push(&text, self.style()),
+            Event::Code(code) => self.push(&code, CODE_STYLE),
+            Event::InlineHtml(html) => self.push(&html, MUTED_STYLE),
+            Event::Html(html) => self.push_lines(&html, MUTED_STYLE),
+            Event::SoftBreak => self.push(" ", self.style()),
+            Event::HardBreak => self.end_line(),
+            Event::Rule => {
+                self.start_block();
+                self.push("───", MUTED_STYLE);
+                self.end_block();
+            }
+            Event::TaskListMarker(done) => self.push(if done { "☑ " } else { "☐ " }, self.style()),
+            _ => (),
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => self.start_block(),
+            Tag::Heading { level, .. } => {
+                self.start_block();
+
+                let style = Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+                self.styles.push(match level {
+                    HeadingLevel::H1 => style.add_modifier(Modifier::UNDERLINED),
+                    _ => style,
+                });
+            }
+            Tag::BlockQuote(_) => {
+                self.start_block();
+                self.quote_depth += 1;
+            }
+            Tag::CodeBlock(_) => {
+                self.start_block();
+                self.in_code_block = true;
+            }
+            Tag::List(first_number) => {
+                if self.lists.is_empty() {
+                    self.start_block();
+                } else {
+                    self.end_line();
+                }
+
+                self.lists.push(first_number);
+            }
+            Tag::Item => {
+                self.end_line();
+
+                self.marker = Some(match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "• ".to_string(),
+                });
+            }
+            Tag::Emphasis => self.push_style(Style::new().add_modifier(Modifier::ITALIC)),
+            Tag::Strong => self.push_style(Style::new().add_modifier(Modifier::BOLD)),
+            Tag::Strikethrough => self.push_style(Style::new().add_modifier(Modifier::CROSSED_OUT)),
+            Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. } => {
+                self.push_style(LINK_STYLE);
+                self.links.push((dest_url.to_string(), String::new()));
+            }
+            _ => (),
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::CodeBlock => {
+                self.in_code_block = false;
+                self.end_block();
+            }
+            TagEnd::Heading(_) => {
+                self.styles.pop();
+                self.end_block();
+            }
+            TagEnd::BlockQuote(_) => {
+                self.end_line();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+                self.end_block();
+            }
+            TagEnd::List(_) => {
+                self.end_line();
+                self.lists.pop();
+                self.end_block();
+            }
+            TagEnd::Item => self.end_line(),
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => {
+                self.styles.pop();
+            }
+            TagEnd::Link | TagEnd::Image => {
+                self.styles.pop();
+
+                // Bare links already show where they go.
+                if let Some((url, text)) = self.links.pop()
+                    && !url.is_empty()
+                    && url != text
+                {
+                    self.push(&format!(" ({url})"), MUTED_STYLE);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn style(&self) -> Style {
+        self.styles.last().copied().unwrap_or_default()
+    }
+
+    /// Nests `style` inside the styles in effect.
+    fn push_style(&mut self, style: Style) {
+        self.styles.push(self.style().patch(style));
+    }
+
+    fn push(&mut self, text: &str, style: Style) {
+        if self.spans.is_empty() {
+            self.start_line();
+        }
+
+        for (_, link_text) in &mut self.links {
+            link_text.push_str(text);
+        }
+
+        self.spans.push(Span::styled(text.to_string(), style));
+    }
+
+    /// Pushes text that keeps its line breaks, like code.
+    fn push_lines(&mut self, text: &str, style: Style) {
+        for line in text.split_inclusive('\n') {
+            self.push(line.trim_end_matches('\n'), style);
+
+            if line.ends_with('\n') {
+                self.end_line();
+            }
+        }
+    }
+
+    /// Starts a line inside the quotes and lists the text is in.
+    fn start_line(&mut self) {
+        if self.quote_depth > 0 {
+            self.spans
+                .push(Span::styled("│ ".repeat(self.quote_depth), MUTED_STYLE));
+        }
+
+        if !self.lists.is_empty() {
+            let indent = "  ".repeat(self.lists.len() - 1);
+            let marker = self.marker.take().unwrap_or_else(|| "  ".to_string());
+            self.spans.push(Span::from(format!("{indent}{marker}")));
+        }
+    }
+
+    fn end_line(&mut self) {
+        if !self.spans.is_empty() {
+            self.lines.push(Line::from(std::mem::take(&mut self.spans)));
+        }
+    }
+
+    fn start_block(&mut self) {
+        self.end_line();
+
+        if self.gap && !self.lines.is_empty() {
+            self.lines.push(Line::default());
+        }
+
+        self.gap = false;
+    }
+
+    /// Ends a block. Blocks inside lists follow each other without a gap.
+    fn end_block(&mut self) {
+        self.end_line();
+        self.gap = self.lists.is_empty();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The rendered lines as plain text.
+    fn lines(markdown: &str) -> Vec<String> {
+        render(markdown)
+            .lines
+            .iter()
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn nested_lists_are_indented() {
+        assert_eq!(
+            lines("- one\n  - two\n    - three\n- four"),
+            vec!["• one", "  • two", "    • three", "• four"]
+        );
+
+        // Paragraphs in a loose item line up under its text.
+        assert_eq!(
+            lines("- first\n\n  more\n- second"),
+            vec!["• first", "  more", "• second"]
+        );
+    }
+
+    #[test]
+    fn numbered_lists_count_from_their_first_number() {
+        assert_eq!(
+            lines("3. three\n4. four\n   1. nested\n   2. again\n5. five"),
+            vec![
+                "3. three",
+                "4. four",
+                "  1. nested",
+                "  2. again",
+                "5. five"
+            ]
+        );
+        assert_eq!(
+            lines("- [x] done\n- [ ] open"),
+            vec!["• ☑ done", "• ☐ open"]
+        );
+    }
+
+    #[test]
+    fn code_blocks_keep_their_lines() {
+        let text = render("Before\n\n```rust\nlet x = 1;\n  indented\n```\nAfter `inline`");
+
+        assert_eq!(
+            lines("Before\n\n```rust\nlet x = 1;\n  indented\n```\nAfter `inline`"),
+            vec!["Before", "", "let x = 1;", "  indented", "", "After inline"]
+        );
+        assert_eq!(text.lines[3].spans[0].style, CODE_STYLE);
+        assert_eq!(text.lines[5].spans[1].style, CODE_STYLE);
+    }
+
+    #[test]
+    fn links_show_where_they_go() {
+        let text = render("See [the docs](https://example.com/docs) or <https://example.com>.");
+
+        assert_eq!(
+            text.lines[0].to_string(),
+            "See the docs (https://example.com/docs) or https://example.com."
+        );
+
+        let link = &text.lines[0].spans[1];
+        assert_eq!(link.content, "the docs");
+        assert_eq!(link.style, LINK_STYLE);
+        assert_eq!(text.lines[0].spans[2].style, MUTED_STYLE);
+
+        // A link without a target shows just its text.
+        assert_eq!(lines("[nowhere]()"), vec!["nowhere"]);
+    }
+}
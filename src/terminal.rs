@@ -0,0 +1,102 @@
+use crossterm::event::{
+    DisableBracketedPaste, EnableBracketedPaste, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::execute;
+use crossterm::terminal::{EnterAlternateScreen, enable_raw_mode, supports_keyboard_enhancement};
+use ratatui::DefaultTerminal;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tempfile::NamedTempFile;
+
+static KEYBOARD_ENHANCED: AtomicBool = AtomicBool::new(false);
+
+/// Sets the terminal up like `ratatui::init`, and also asks it to report
+/// pasted text as a whole and to tell Shift-Enter apart from Enter. Terminals
+/// that can't do either still work, without those extras.
+pub fn init() -> DefaultTerminal {
+    let terminal = ratatui::init();
+    enable_extras();
+    terminal
+}
+
+pub fn restore() {
+    disable_extras();
+    ratatui::restore();
+}
+
+/// Hands the terminal over to another program, such as a text editor.
+pub fn suspend() {
+    restore();
+}
+
+/// Takes the terminal back after [`suspend`], redrawing everything.
+pub fn resume(terminal: &mut DefaultTerminal) -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    enable_extras();
+    terminal.clear()
+}
+
+/// Writes `text` to `file`, opens it in `$VISUAL`, `$EDITOR` or `vi` and
+/// returns what it holds once the editor exits. Exiting with an error, like
+/// `:cq` in vim, counts as cancelling.
+pub fn edit_text(
+    terminal: &mut DefaultTerminal,
+    file: &mut NamedTempFile,
+    text: &str,
+) -> io::Result<String> {
+    file.write_all(text.as_bytes())?;
+    file.flush()?;
+    let path = file.path();
+
+    let editor = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+
+    suspend();
+    let status = Command::new(program).args(words).arg(path).status();
+    resume(terminal)?;
+
+    let status =
+        status.map_err(|error| io::Error::new(error.kind(), format!("{program}: {error}")))?;
+
+    if !status.success() {
+        return Err(io::Error::other(format!("{program} exited with {status}")));
+    }
+
+    fs::read_to_string(path)
+}
+
+fn enable_extras() {
+    let mut stdout = io::stdout();
+
+    let _ = execute!(stdout, EnableBracketedPaste);
+
+    if supports_keyboard_enhancement().unwrap_or(false)
+        && execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )
+        .is_ok()
+    {
+        KEYBOARD_ENHANCED.store(true, Ordering::Relaxed);
+    }
+}
+
+fn disable_extras() {
+    let mut stdout = io::stdout();
+
+    if KEYBOARD_ENHANCED.swap(false, Ordering::Relaxed) {
+        let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+    }
+
+    let _ = execute!(stdout, DisableBracketedPaste);
+}
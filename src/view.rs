@@ -0,0 +1,202 @@
+use chrono::{Days, NaiveDate};
+use just_lists_core::list::List;
+use just_lists_core::list_item::{ListItem, State};
+use just_lists_core::workflow::Workflow;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+/// How siblings are ordered on screen.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SortMode {
+    /// The order items have in the list.
+    Manual,
+    /// Earliest due date first, undated items last.
+    DueDate,
+    /// Highest priority first.
+    Priority,
+}
+
+impl SortMode {
+    pub fn next(self) -> SortMode {
+        match self {
+            SortMode::Manual => SortMode::DueDate,
+            SortMode::DueDate => SortMode::Priority,
+            SortMode::Priority => SortMode::Manual,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortMode::Manual => "list order",
+            SortMode::DueDate => "due date",
+            SortMode::Priority => "priority",
+        }
+    }
+}
+
+/// Which items are shown based on their due date.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DueFilter {
+    All,
+    Overdue,
+    /// Open items due today or earlier.
+    DueToday,
+    /// Open items due within the next seven days, or earlier.
+    DueThisWeek,
+    HasDueDate,
+}
+
+impl DueFilter {
+    pub fn next(self) -> DueFilter {
+        match self {
+            DueFilter::All => DueFilter::Overdue,
+            DueFilter::Overdue => DueFilter::DueToday,
+            DueFilter::DueToday => DueFilter::DueThisWeek,
+            DueFilter::DueThisWeek => DueFilter::HasDueDate,
+            DueFilter::HasDueDate => DueFilter::All,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DueFilter::All => "all items",
+            DueFilter::Overdue => "overdue",
+            DueFilter::DueToday => "due today",
+            DueFilter::DueThisWeek => "due this week",
+            DueFilter::HasDueDate => "with a due date",
+        }
+    }
+
+    fn matches(self, item: &ListItem, today: NaiveDate, workflow: &Workflow) -> bool {
+        let due_by = |last_day: NaiveDate| {
+            !workflow.is_done(&item.state) && item.due.is_some_and(|due| due <= last_day)
+        };
+
+        match self {
+            DueFilter::All => true,
+            DueFilter::Overdue => item.is_overdue(today, workflow),
+            DueFilter::DueToday => due_by(today),
+            DueFilter::DueThisWeek => due_by(today.checked_add_days(Days::new(6)).unwrap_or(today)),
+            DueFilter::HasDueDate => item.due.is_some(),
+        }
+    }
+}
+
+/// Sorting and filtering applied to what is displayed. The list itself is
+/// never reordered.
+pub struct ViewOptions {
+    pub sort: SortMode,
+    pub due_filter: DueFilter,
+    /// Only show items carrying this tag.
+    pub tag_filter: Option<String>,
+    /// Only show items in this state.
+    pub state_filter: Option<State>,
+    /// Hide items in a finished state. Unlike the filters this is meant to
+    /// stay on, so it does not expand anything and adding items keeps it.
+    pub hide_done: bool,
+}
+
+impl ViewOptions {
+    pub fn new() -> ViewOptions {
+        ViewOptions {
+            sort: SortMode::Manual,
+            due_filter: DueFilter::All,
+            tag_filter: None,
+            state_filter: None,
+            hide_done: false,
+        }
+    }
+
+    pub fn is_filtering(&self) -> bool {
+        self.due_filter != DueFilter::All
+            || self.tag_filter.is_some()
+            || self.state_filter.is_some()
+    }
+
+    /// Descriptions of the active sort and filters, for the list title.
+    pub fn describe(&self) -> Vec<String> {
+        let mut descriptions = Vec::new();
+
+        if self.sort != SortMode::Manual {
+            descriptions.push(format!("[Sorted by {}]", self.sort.label()));
+        }
+
+        if self.due_filter != DueFilter::All {
+            descriptions.push(format!("[Showing {}]", self.due_filter.label()));
+        }
+
+        if let Some(tag) = &self.tag_filter {
+            descriptions.push(format!("[Tagged #{tag}]"));
+        }
+
+        if let Some(state) = &self.state_filter {
+            descriptions.push(format!("[Only {state}]"));
+        }
+
+        if self.hide_done {
+            descriptions.push("[Hiding completed]".to_string());
+        }
+
+        descriptions
+    }
+
+    fn matches(&self, item: &ListItem, today: NaiveDate, workflow: &Workflow) -> bool {
+        self.due_filter.matches(item, today, workflow)
+            && self.tag_filter.as_ref().is_none_or(|tag| item.has_tag(tag))
+            && self
+                .state_filter
+                .as_ref()
+                .is_none_or(|state| item.state == *state)
+            && !(self.hide_done && workflow.is_done(&item.state))
+    }
+
+    /// The `items` to show, in display order. While filtering, an item is
+    /// shown if it or one of its descendants matches, so matches are always
+    /// seen in context. In the same way, finished items are only hidden once
+    /// everything below them is finished too.
+    pub fn visible<'a>(
+        &self,
+        list: &'a List,
+        items: Vec<&'a ListItem>,
+        today: NaiveDate,
+        workflow: &Workflow,
+    ) -> Vec<&'a ListItem> {
+        let mut items: Vec<&ListItem> = if self.is_filtering() || self.hide_done {
+            items
+                .into_iter()
+                .filter(|item| {
+                    self.subtree_matches(list, item, today, workflow, &mut HashSet::new())
+                })
+                .collect()
+        } else {
+            items
+        };
+
+        match self.sort {
+            SortMode::Manual => (),
+            SortMode::DueDate => items.sort_by_key(|item| (item.due.is_none(), item.due)),
+            SortMode::Priority => items.sort_by_key(|item| Reverse(item.priority)),
+        }
+
+        items
+    }
+
+    fn subtree_matches<'a>(
+        &self,
+        list: &'a List,
+        item: &'a ListItem,
+        today: NaiveDate,
+        workflow: &Workflow,
+        visited: &mut HashSet<&'a str>,
+    ) -> bool {
+        if !visited.insert(&item.id) {
+            return false;
+        }
+
+        self.matches(item, today, workflow)
+            || list
+                .get_children(item)
+                .into_iter()
+                .any(|child| self.subtree_matches(list, child, today, workflow, visited))
+    }
+}
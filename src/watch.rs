@@ -0,0 +1,58 @@
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::ffi::OsString;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+
+/// Watches a list file for changes made by other programs.
+///
+/// The parent directory is watched rather than the file itself, because
+/// saves (ours and those of most editors and sync tools) replace the file
+/// with a rename, which would end a watch on the old file.
+pub struct FileWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    file_name: OsString,
+}
+
+impl FileWatcher {
+    pub fn new(path: &Path) -> notify::Result<FileWatcher> {
+        let file_name = path.file_name().map(OsString::from).unwrap_or_default();
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(directory, RecursiveMode::NonRecursive)?;
+
+        Ok(FileWatcher {
+            _watcher: watcher,
+            events,
+            file_name,
+        })
+    }
+
+    /// Drains the pending events, returning true if any of them touched the
+    /// list file.
+    pub fn has_changed(&self) -> bool {
+        let mut changed = false;
+
+        while let Ok(event) = self.events.try_recv() {
+            let Ok(event) = event else {
+                continue;
+            };
+
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+
+            changed |= event
+                .paths
+                .iter()
+                .any(|path| path.file_name() == Some(self.file_name.as_os_str()));
+        }
+
+        changed
+    }
+}
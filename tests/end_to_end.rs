@@ -9,7 +9,7 @@ fn golden_test() -> Result<(), String> {
 
     assert!(check_vhs.status.success(), "vhs command is not available");
 
-    let mut temp_folder = tempfile::tempdir().expect("Unable to create temporary directory.");
+    let temp_folder = tempfile::tempdir().expect("Unable to create temporary directory.");
     _ = fs::copy(
         "./docs/demo.tape",
         format!("{}/demo.tape", temp_folder.path().to_str().unwrap()),